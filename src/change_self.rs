@@ -1,19 +1,30 @@
 use std::collections::{HashMap, HashSet};
 
-use proc_macro2::Span;
+use proc_macro2::{Group, Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
 use syn::{
     fold::Fold,
+    parse::{ParseStream, Parser},
+    parse_quote,
+    punctuated::{Pair, Punctuated},
     spanned::Spanned,
-    token::{Colon, Gt, Lt, Mut, PathSep, SelfValue},
-    AngleBracketedGenericArguments, FnArg, GenericArgument, Ident, Pat, PatIdent, PatType, Path,
-    PathArguments, PathSegment, Receiver, Type, TypePath, TypeReference,
+    token::{As, Colon, Comma, Gt, Lt, Mut, PathSep, SelfValue, Semi},
+    AngleBracketedGenericArguments, AssocType, Constraint, Error, Expr, ExprLit, ExprPath, FnArg,
+    GenericArgument, Generics, Ident, Item, Lit, LitStr, Pat, PatIdent, PatType, Path,
+    PathArguments, QSelf, Receiver, TraitBound, TraitBoundModifier, Type, TypeParamBound, TypePath,
+    WherePredicate,
 };
 
-#[derive(Debug, Clone)]
+use crate::transform::lifetimes_first;
+
+#[derive(Clone)]
 pub struct ChangeSelfToContext {
-    pub local_idents: HashMap<Ident, (bool, Vec<Ident>)>,
+    pub local_idents: HashMap<Ident, (bool, Vec<GenericArgument>)>,
+    pub local_consts: HashSet<Ident>,
+    pub const_params: HashMap<Ident, Type>,
     pub replaced: bool,
     pub found_idents: HashSet<Ident>,
+    pub maybe_unsized: bool,
 }
 
 impl Fold for ChangeSelfToContext {
@@ -37,18 +48,33 @@ impl Fold for ChangeSelfToContext {
             .map(|seg| seg.ident == "Self")
             .unwrap_or(false)
         {
-            self.replaced = true;
-            if let Some(seg) = i.segments.get(1) {
-                if let Some((has_context, other_generics)) = self.local_idents.get(&seg.ident) {
-                    let span = seg.ident.span();
-                    i.segments = i.segments.into_iter().skip(1).collect();
-                    i.segments[0].arguments = prepend_generics(
-                        i.segments[0].arguments.clone(),
+            match i
+                .segments
+                .get(1)
+                .and_then(|seg| self.local_idents.get(&seg.ident))
+            {
+                Some((has_context, other_generics)) => {
+                    let span = i.segments[1].ident.span();
+                    // the generics of the local item are needed as well
+                    if *has_context {
+                        self.replaced = true;
+                        self.found_idents.insert(Ident::new("Self", span));
+                    }
+                    self.found_idents
+                        .extend(other_generics.iter().filter_map(|arg| match arg {
+                            GenericArgument::Type(Type::Path(p)) => p.path.get_ident().cloned(),
+                            _ => None,
+                        }));
+                    let arguments = prepend_generics(
+                        i.segments[1].arguments.clone(),
                         *has_context,
                         other_generics,
                         span,
                     );
+                    i.segments = i.segments.into_iter().skip(1).collect();
+                    i.segments[0].arguments = arguments;
                 }
+                None => self.replaced = true,
             }
         }
         i.segments = i
@@ -58,6 +84,28 @@ impl Fold for ChangeSelfToContext {
             .collect();
         i
     }
+    // nested items (fns, impls, structs, ...) have a Self of their own, if any
+    fn fold_item(&mut self, i: Item) -> Item {
+        i
+    }
+    fn fold_expr(&mut self, i: Expr) -> Expr {
+        match i {
+            // local consts are the consts of structs in the module
+            Expr::Path(p)
+                if p.qself.is_none()
+                    && p.path.segments.len() == 2
+                    && p.path.segments[0].ident == "Self"
+                    && self.local_consts.contains(&p.path.segments[1].ident) =>
+            {
+                let mut p = self.fold_expr_path(p);
+                p.path
+                    .segments
+                    .push(Ident::new("VALUE", Span::call_site()).into());
+                Expr::Path(p)
+            }
+            other => syn::fold::fold_expr(self, other),
+        }
+    }
     fn fold_fn_arg(&mut self, i: syn::FnArg) -> syn::FnArg {
         match i {
             FnArg::Receiver(Receiver {
@@ -65,123 +113,400 @@ impl Fold for ChangeSelfToContext {
                 reference,
                 mutability,
                 self_token,
+                ty,
                 ..
             }) => {
                 self.replaced = true;
-                FnArg::Typed(replace_reciever(attrs, reference, mutability, self_token))
+                let ty = self.fold_type(*ty);
+                FnArg::Typed(replace_reciever(
+                    attrs, reference, mutability, self_token, ty,
+                ))
             }
             FnArg::Typed(t) => FnArg::Typed(self.fold_pat_type(t)),
         }
     }
+    fn fold_macro(&mut self, mut i: syn::Macro) -> syn::Macro {
+        let format_string = format_macro(&i.path);
+        i.path = self.fold_path(i.path);
+        // arguments are folded like any other expressions, where they parse as such (`a, b` or `x; n`)
+        let repeat = |input: ParseStream| {
+            Ok((
+                input.parse::<Expr>()?,
+                input.parse::<Semi>()?,
+                input.parse::<Expr>()?,
+            ))
+        };
+        i.tokens = if let Ok(args) =
+            Punctuated::<Expr, Comma>::parse_terminated.parse2(i.tokens.clone())
+        {
+            args.into_pairs()
+                .enumerate()
+                .map(|(position, pair)| {
+                    let (arg, comma) = pair.into_tuple();
+                    let arg = match arg {
+                        Expr::Lit(ExprLit {
+                            attrs,
+                            lit: Lit::Str(lit),
+                        }) if format_string == Some(position) => Expr::Lit(ExprLit {
+                            attrs,
+                            lit: Lit::Str(self.fold_format_string(lit)),
+                        }),
+                        arg => self.fold_expr(arg),
+                    };
+                    Pair::new(arg, comma)
+                })
+                .collect::<Punctuated<Expr, Comma>>()
+                .into_token_stream()
+        } else if let Ok((value, semi, len)) = repeat.parse2(i.tokens.clone()) {
+            let (value, len) = (self.fold_expr(value), self.fold_expr(len));
+            quote! {#value #semi #len}
+        } else {
+            self.fold_tokens(i.tokens)
+        };
+        i
+    }
+}
+impl ChangeSelfToContext {
+    /// Replace self/Self in tokens, that can't be parsed
+    fn fold_tokens(&mut self, tokens: TokenStream) -> TokenStream {
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                TokenTree::Group(g) => {
+                    let mut new = Group::new(g.delimiter(), self.fold_tokens(g.stream()));
+                    new.set_span(g.span());
+                    TokenTree::Group(new)
+                }
+                TokenTree::Ident(i) => TokenTree::Ident(self.fold_ident(i)),
+                other => other,
+            })
+            .collect()
+    }
+    /// Replace captured self in format strings (`{self:?}` -> `{context:?}`)
+    fn fold_format_string(&mut self, lit: LitStr) -> LitStr {
+        let value = lit.value();
+        let mut out = String::with_capacity(value.len());
+        let mut rest = value.as_str();
+        while let Some(pos) = rest.find('{') {
+            out.push_str(&rest[..pos]);
+            rest = &rest[pos..];
+            if rest.starts_with("{{") {
+                out.push_str("{{");
+                rest = &rest[2..];
+            } else if rest.starts_with("{self}") || rest.starts_with("{self:") {
+                self.replaced = true;
+                out.push_str("{context");
+                rest = &rest[5..];
+            } else {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+        out.push_str(rest);
+        LitStr::new(&out, lit.span())
+    }
 }
+/// Std macros with a format string, and its position
+const FORMAT_MACROS: &[(&str, usize)] = &[
+    ("format", 0),
+    ("format_args", 0),
+    ("print", 0),
+    ("println", 0),
+    ("eprint", 0),
+    ("eprintln", 0),
+    ("panic", 0),
+    ("unreachable", 0),
+    ("todo", 0),
+    ("unimplemented", 0),
+    ("write", 1),
+    ("writeln", 1),
+    ("assert", 1),
+    ("debug_assert", 1),
+    ("assert_eq", 2),
+    ("assert_ne", 2),
+    ("debug_assert_eq", 2),
+    ("debug_assert_ne", 2),
+];
+
+/// The position of the format string, if the macro is one of [`FORMAT_MACROS`] (`format!` or `std::format!`)
+fn format_macro(path: &Path) -> Option<usize> {
+    let segments: Vec<_> = path.segments.iter().collect();
+    let name = match segments[..] {
+        [name] => name,
+        [krate, name] if ["std", "core", "alloc"].iter().any(|k| krate.ident == k) => name,
+        _ => return None,
+    };
+    FORMAT_MACROS
+        .iter()
+        .find(|(std, _)| name.ident == std)
+        .map(|(_, format_string)| *format_string)
+}
+
 pub fn prepend_generics(
     arguments: PathArguments,
     has_context: bool,
-    other_generics: &[Ident],
+    other_generics: &[GenericArgument],
     span: Span,
 ) -> PathArguments {
-    match arguments {
-        PathArguments::AngleBracketed(mut args) => {
-            args.args = has_context
-                .then_some([GenericArgument::Type(Type::Path(TypePath {
-                    qself: None,
-                    path: Path {
-                        leading_colon: None,
-                        segments: [PathSegment {
-                            ident: Ident::new("Context", span),
-                            arguments: PathArguments::None,
-                        }]
-                        .into_iter()
-                        .collect(),
-                    },
-                }))])
-                .into_iter()
-                .flatten()
-                .chain(other_generics.iter().map(|gen| {
-                    GenericArgument::Type(Type::Path(TypePath {
-                        qself: None,
-                        path: Path::from(gen.clone()),
-                    }))
-                }))
-                .chain(args.args)
-                .collect();
-            PathArguments::AngleBracketed(args)
-        }
-        _ => PathArguments::AngleBracketed(AngleBracketedGenericArguments {
-            colon2_token: Some(PathSep::default()),
-            lt_token: Lt::default(),
-            args: has_context
-                .then_some([GenericArgument::Type(Type::Path(TypePath {
-                    qself: None,
-                    path: Path {
-                        leading_colon: None,
-                        segments: [PathSegment {
-                            ident: Ident::new("Context", span),
-                            arguments: PathArguments::None,
-                        }]
-                        .into_iter()
-                        .collect(),
-                    },
-                }))])
-                .into_iter()
-                .flatten()
-                .chain(other_generics.iter().map(|gen| {
-                    GenericArgument::Type(Type::Path(TypePath {
-                        qself: None,
-                        path: Path::from(gen.clone()),
-                    }))
-                }))
-                .collect(),
-            gt_token: Gt::default(),
-        }),
-    }
+    let (colon2_token, args) = match arguments {
+        PathArguments::AngleBracketed(args) => (args.colon2_token, args.args),
+        _ => (Some(PathSep::default()), Punctuated::new()),
+    };
+    let mut args: Vec<_> = has_context
+        .then_some(GenericArgument::Type(Type::Path(TypePath {
+            qself: None,
+            path: Path::from(Ident::new("Context", span)),
+        })))
+        .into_iter()
+        .chain(other_generics.iter().cloned())
+        .chain(args)
+        .collect();
+    lifetimes_first(&mut args);
+    PathArguments::AngleBracketed(AngleBracketedGenericArguments {
+        colon2_token,
+        lt_token: Lt::default(),
+        args: args.into_iter().collect(),
+        gt_token: Gt::default(),
+    })
 }
+/// `self` becomes `context`, its (possibly implicit) type is folded like any other
 fn replace_reciever(
     attrs: Vec<syn::Attribute>,
     reference: Option<(syn::token::And, Option<syn::Lifetime>)>,
     mutability: Option<Mut>,
     self_token: SelfValue,
+    ty: Type,
 ) -> PatType {
     PatType {
         attrs,
         pat: Box::new(Pat::Ident(PatIdent {
             attrs: vec![],
             by_ref: None,
-            mutability,
+            // `&mut self` is a mutable reference, not a mutable binding
+            mutability: mutability.filter(|_| reference.is_none()),
             ident: Ident::new("context", self_token.span()),
             subpat: None,
         })),
         colon_token: Colon::default(),
-        ty: Box::new(match reference {
-            Some((and, lifetime)) => Type::Reference(TypeReference {
-                and_token: and,
-                lifetime,
-                mutability,
-                elem: Box::new(Type::Path(TypePath {
-                    qself: None,
-                    path: Path {
-                        leading_colon: None,
-                        segments: [PathSegment {
-                            ident: Ident::new("Context", self_token.span()),
-                            arguments: syn::PathArguments::None,
-                        }]
-                        .into_iter()
-                        .collect(),
+        ty: Box::new(ty),
+    }
+}
+
+/// `Self::Assoc` of a trait bounding Self -> `<Self as Trait>::Assoc`
+///
+/// Neither the free functions nor the impls could resolve it otherwise.
+pub struct QualifySelf {
+    /// traits bounding Self in the where clause of the impl
+    pub traits: Vec<Path>,
+    /// items of the impl itself
+    pub local_idents: HashSet<Ident>,
+    /// an associated item, whose trait is unclear
+    pub error: Option<Error>,
+}
+
+impl Fold for QualifySelf {
+    fn fold_type_path(&mut self, i: TypePath) -> TypePath {
+        match self.qualify(&i.qself, &i.path, 2) {
+            Some((qself, path)) => TypePath {
+                qself: Some(qself),
+                path,
+            },
+            None => syn::fold::fold_type_path(self, i),
+        }
+    }
+    fn fold_expr_path(&mut self, i: ExprPath) -> ExprPath {
+        // `Self::item()` may be a method of any trait, `Self::Assoc::item()` is of a type
+        match self.qualify(&i.qself, &i.path, 3) {
+            Some((qself, path)) => ExprPath {
+                attrs: i.attrs,
+                qself: Some(qself),
+                path,
+            },
+            None => syn::fold::fold_expr_path(self, i),
+        }
+    }
+    fn fold_item(&mut self, i: Item) -> Item {
+        i
+    }
+}
+
+impl QualifySelf {
+    fn qualify(
+        &mut self,
+        qself: &Option<QSelf>,
+        path: &Path,
+        min_len: usize,
+    ) -> Option<(QSelf, Path)> {
+        if qself.is_some()
+            || path.segments.len() < min_len
+            || path.segments[0].ident != "Self"
+            || self.local_idents.contains(&path.segments[1].ident)
+        {
+            return None;
+        }
+        let assoc = &path.segments[1].ident;
+        match owning_traits(&self.traits, assoc)[..] {
+            [trait_] => {
+                let trait_ = without_bindings(trait_.clone());
+                let position = trait_.segments.len();
+                let rest: Vec<_> = path
+                    .segments
+                    .iter()
+                    .skip(1)
+                    .map(|seg| self.fold_path_segment(seg.clone()))
+                    .collect();
+                Some((
+                    QSelf {
+                        lt_token: Lt::default(),
+                        ty: Box::new(parse_quote! {Self}),
+                        position,
+                        as_token: Some(As::default()),
+                        gt_token: Gt::default(),
                     },
-                })),
-            }),
-            None => Type::Path(TypePath {
-                qself: None,
-                path: Path {
-                    leading_colon: None,
-                    segments: [PathSegment {
-                        ident: Ident::new("Context", self_token.span()),
-                        arguments: syn::PathArguments::None,
-                    }]
+                    Path {
+                        leading_colon: trait_.leading_colon,
+                        segments: trait_.segments.into_iter().chain(rest).collect(),
+                    },
+                ))
+            }
+            // left to the compiler
+            [] => None,
+            [first, second, ..] => {
+                let name = |trait_: &Path| {
+                    trait_
+                        .segments
+                        .last()
+                        .expect("paths are never empty")
+                        .ident
+                        .clone()
+                };
+                let (first, second) = (name(first), name(second));
+                self.error.get_or_insert(Error::new(
+                    assoc.span(),
+                    format!(
+                        "`Self::{assoc}` could be of `{first}` or `{second}`, use `<Self as {first}>::{assoc}`"
+                    ),
+                ));
+                None
+            }
+        }
+    }
+}
+
+/// The traits bounding Self in the where clause (without `?Sized` or higher-ranked ones)
+pub fn self_traits(generics: &Generics) -> Vec<Path> {
+    let mut traits: Vec<Path> = vec![];
+    for pred in generics.where_clause.iter().flat_map(|w| &w.predicates) {
+        let WherePredicate::Type(pred) = pred else {
+            continue;
+        };
+        if !matches!(&pred.bounded_ty, Type::Path(p) if p.qself.is_none() && p.path.is_ident("Self"))
+        {
+            continue;
+        }
+        for bound in &pred.bounds {
+            match bound {
+                TypeParamBound::Trait(TraitBound {
+                    modifier: TraitBoundModifier::None,
+                    lifetimes: None,
+                    path,
+                    ..
+                }) if !traits.iter().any(|other| {
+                    other.to_token_stream().to_string() == path.to_token_stream().to_string()
+                }) =>
+                {
+                    traits.push(path.clone())
+                }
+                _ => {}
+            }
+        }
+    }
+    traits
+}
+
+/// Removes `Self: ?Sized` from the where clause (it can only be declared on Context), telling if it was there
+pub fn take_maybe_sized(generics: &mut Generics) -> bool {
+    let Some(where_clause) = &mut generics.where_clause else {
+        return false;
+    };
+    let mut maybe_sized = false;
+    where_clause.predicates = std::mem::take(&mut where_clause.predicates)
+        .into_iter()
+        .filter_map(|pred| match pred {
+            WherePredicate::Type(mut pred) if is_self(&pred.bounded_ty) => {
+                let bounds = pred.bounds.len();
+                pred.bounds = std::mem::take(&mut pred.bounds)
                     .into_iter()
-                    .collect(),
-                },
-            }),
-        }),
+                    .filter(|bound| !is_maybe_sized(bound))
+                    .collect();
+                maybe_sized |= pred.bounds.len() < bounds;
+                (!pred.bounds.is_empty()).then_some(WherePredicate::Type(pred))
+            }
+            other => Some(other),
+        })
+        .collect();
+    maybe_sized
+}
+
+pub fn is_self(ty: &Type) -> bool {
+    matches!(ty, Type::Path(p) if p.qself.is_none() && p.path.is_ident("Self"))
+}
+
+/// `?Sized`
+fn is_maybe_sized(bound: &TypeParamBound) -> bool {
+    matches!(bound, TypeParamBound::Trait(t)
+        if matches!(t.modifier, TraitBoundModifier::Maybe(_))
+            && t.path.segments.last().is_some_and(|seg| seg.ident == "Sized"))
+}
+
+/// The traits, which have the associated item for sure
+///
+/// Those fixing it (`Trait<Assoc = Type>`), or else the only trait bounding Self.
+/// Without either, the compiler resolves it.
+fn owning_traits<'a>(traits: &'a [Path], assoc: &Ident) -> Vec<&'a Path> {
+    let args = |trait_: &'a Path| match &trait_
+        .segments
+        .last()
+        .expect("paths are never empty")
+        .arguments
+    {
+        PathArguments::AngleBracketed(args) => Some(args.args.iter()),
+        _ => None,
+    };
+    let fixed: Vec<_> = traits
+        .iter()
+        .filter(|trait_| {
+            args(trait_).into_iter().flatten().any(|arg| match arg {
+                GenericArgument::AssocType(AssocType { ident, .. })
+                | GenericArgument::Constraint(Constraint { ident, .. }) => ident == assoc,
+                _ => false,
+            })
+        })
+        .collect();
+    match traits {
+        [trait_] if fixed.is_empty() => vec![trait_],
+        _ => fixed,
+    }
+}
+
+/// `Trait<T, Assoc = U>` -> `Trait<T>` (qualified paths can't fix associated types)
+fn without_bindings(mut trait_: Path) -> Path {
+    let last = trait_.segments.last_mut().expect("paths are never empty");
+    if let PathArguments::AngleBracketed(args) = &mut last.arguments {
+        args.args = std::mem::take(&mut args.args)
+            .into_iter()
+            .filter(|arg| {
+                !matches!(
+                    arg,
+                    GenericArgument::AssocType(_)
+                        | GenericArgument::AssocConst(_)
+                        | GenericArgument::Constraint(_)
+                )
+            })
+            .collect();
+        if args.args.is_empty() {
+            last.arguments = PathArguments::None;
+        }
     }
+    trait_
 }
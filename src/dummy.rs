@@ -1,61 +1,103 @@
-use proc_macro2::Span;
-use quote::quote;
+use std::collections::HashMap;
+
+use crate::{
+    mac::{call_part, crate_scope, generate_fn, impl_macro, item_ident},
+    transform::impl_param,
+};
+use proc_macro2::{Group, Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
 use syn::{
     fold::Fold,
     parse_quote,
     punctuated::Punctuated,
     spanned::Spanned,
-    token::{Brace, Comma, For, Paren},
-    Block, Error, GenericArgument, Ident, ImplItem, Item, ItemImpl, Path, Stmt, Type, TypePath,
-    TypeTuple,
+    token::{Brace, Comma, For, Paren, Plus, Where},
+    Block, Error, FnArg, GenericArgument, Ident, ImplItem, Item, ItemImpl, Pat, PatType, Path,
+    PathArguments, ReturnType, Signature, Stmt, Type, TypeParamBound, TypeTuple, WhereClause,
+    WherePredicate,
 };
 
 pub fn generate_dummy_impl(
     mut imp: ItemImpl,
     trait_: Path,
     ty_generics: Punctuated<GenericArgument, Comma>,
+    const_params: &HashMap<Ident, Type>,
+    renamed: &HashMap<Ident, Ident>,
+    parts: &[Path],
+    base: Option<&Path>,
 ) -> syn::Result<Item> {
-    imp.self_ty = Box::new(Type::Path(TypePath {
-        qself: None,
-        path: Path::from(Ident::new("Dummy", Span::call_site())),
-    }));
+    imp.self_ty = Box::new(parse_quote! {Dummy<Context>});
     imp.trait_ = Some((None, trait_.clone(), For::default()));
     let mut folder = crate::change_self::ChangeSelfToContext {
         local_idents: std::collections::HashMap::new(),
+        local_consts: std::collections::HashSet::new(),
+        const_params: const_params.clone(),
         replaced: false,
         found_idents: std::collections::HashSet::new(),
+        maybe_unsized: true,
     };
-    folder.fold_path(trait_);
+    folder.fold_path(trait_.clone());
+    // impl generics not used by the trait can't be declared on the dummy
+    let undeclared: Box<[_]> = ty_generics
+        .iter()
+        .filter_map(|arg| match arg {
+            GenericArgument::Type(Type::Path(p))
+                if !folder.found_idents.contains(&p.path.segments[0].ident) =>
+            {
+                Some(p.path.segments[0].ident.clone())
+            }
+            _ => None,
+        })
+        .collect();
 
-    let dummy_body: syn::Expr = parse_quote! {
-        unreachable!()
-    };
+    // (the items, which can't be left out, call the ones of the module like the impl macro)
+    let generics = imp.generics.clone();
+    let args: Box<[_]> = ty_generics
+        .iter()
+        .map(|arg| match arg {
+            GenericArgument::Type(Type::Path(p))
+                if undeclared.contains(&p.path.segments[0].ident) =>
+            {
+                parse_quote! {()}
+            }
+            other => other.clone(),
+        })
+        .collect();
     imp.items = imp
         .items
         .into_iter()
-        .map(|item| match item {
-            ImplItem::Fn(mut f) => {
-                f.block = Block {
-                    brace_token: Brace::default(),
-                    stmts: vec![Stmt::Expr(dummy_body.clone(), None)],
-                };
-                ImplItem::Fn(f)
-            }
-            ImplItem::Const(mut c) => {
-                c.expr = dummy_body.clone();
-                ImplItem::Const(c)
-            }
-            ImplItem::Type(mut t) => {
-                t.ty = Type::Tuple(TypeTuple {
-                    paren_token: Paren::default(),
-                    elems: Punctuated::new(),
-                });
-                ImplItem::Type(t)
-            }
-            other => other,
+        .map(|item| {
+            let delegating = match &item {
+                ImplItem::Fn(f) => generate_fn(
+                    f.clone(),
+                    renamed.get(&f.sig.ident).unwrap_or(&f.sig.ident).clone(),
+                    Ident::new("self", Span::call_site()),
+                    generics.clone(),
+                    args.clone(),
+                ),
+                other => other.clone(),
+            };
+            dummy_item(item, &delegating, &undeclared)
         })
+        .chain(parts.iter().map(|part| call_part(part, "dummy")))
         .collect();
-    imp.generics.where_clause = None;
+    // keep the bounds of the impl (Self being the dummy), so the types keep working
+    // (the associated types of composed parts or inherited ones are unknown, so there is no bound then)
+    let self_bound = (parts.is_empty() && base.is_none())
+        .then(|| self_bound(&imp, trait_.clone()))
+        .flatten();
+    imp.generics.where_clause = Some(WhereClause {
+        where_token: Where::default(),
+        predicates: imp
+            .generics
+            .where_clause
+            .map(|w| w.predicates)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|pred| !mentions(pred.to_token_stream(), &undeclared))
+            .chain(self_bound)
+            .collect(),
+    });
     imp.generics.params = ty_generics
         .into_iter()
         .filter_map(|arg| match arg {
@@ -70,21 +112,33 @@ pub fn generate_dummy_impl(
             GenericArgument::Type(Type::Path(p)) => folder
                 .found_idents
                 .contains(&p.path.segments[0].ident)
-                .then_some(Ok(syn::GenericParam::Type(syn::TypeParam {
-                    attrs: vec![],
-                    ident: p.path.segments[0].ident.clone(),
-                    colon_token: None,
-                    bounds: Punctuated::new(),
-                    eq_token: None,
-                    default: None,
-                }))),
+                .then(|| Ok(impl_param(p.path.segments[0].ident.clone(), const_params))),
             o => Some(Err(Error::new(
                 o.span(),
-                "Impl cannot have generics other than type or Lifetime",
+                "Impl cannot have generics other than type, const or Lifetime",
             ))),
         })
         .chain(imp.generics.params.into_iter().map(Ok))
+        .chain([Ok(parse_quote! {Context: ?Sized})])
         .collect::<syn::Result<_>>()?;
+    // the extended impl generates the dummy, so it gets its items and bounds as well
+    if let Some(base) = base {
+        let (mac, args) = impl_macro(base.clone())?;
+        let args = args.map(|args| replace_idents(args, &undeclared));
+        let names = imp.items.iter().filter_map(item_ident);
+        let params = imp.generics.params.iter();
+        let predicates = dummy_predicates(
+            imp.generics
+                .where_clause
+                .into_iter()
+                .flat_map(|w| w.predicates),
+            &trait_,
+        );
+        let items = &imp.items;
+        return Ok(Item::Verbatim(quote! {
+            #mac!(@extend dummy #[allow(unused_variables)] [#(#params,)*] [#(#predicates,)*] [#(#names)*] [#(#items)*] #args Dummy<Context>);
+        }));
+    }
     imp.attrs.push(syn::Attribute {
         pound_token: syn::token::Pound::default(),
         style: syn::AttrStyle::Outer,
@@ -95,5 +149,190 @@ pub fn generate_dummy_impl(
             tokens: quote! {unused_variables},
         }),
     });
+    // the macros of the parts are called in the scope of the crate
+    if !parts.is_empty() {
+        let scope = crate_scope(quote! {crate});
+        return Ok(Item::Verbatim(quote! {
+            const _: () = {
+                #scope
+                #imp
+            };
+        }));
+    }
     Ok(Item::Impl(imp))
 }
+
+/// The item without its implementation, types using undeclared generics become `()`
+///
+/// Functions returning `impl Trait` (other than a Future) are the delegating item,
+/// as `!` would be `()` there.
+pub fn dummy_item(item: ImplItem, delegating: &ImplItem, undeclared: &[Ident]) -> ImplItem {
+    let dummy_body: syn::Expr = parse_quote! {
+        unreachable!()
+    };
+    match item {
+        ImplItem::Fn(f) if returns_opaque(&f.sig) => delegating.clone(),
+        ImplItem::Fn(mut f) => {
+            // `!` doesn't implement Future, so return one that never completes
+            let returns_future = impl_trait_bounds(&f.sig).is_some();
+            immutable_args(&mut f.sig);
+            f.block = Block {
+                brace_token: Brace::default(),
+                stmts: vec![Stmt::Expr(
+                    if returns_future {
+                        parse_quote!(::core::future::pending())
+                    } else {
+                        dummy_body
+                    },
+                    None,
+                )],
+            };
+            ImplItem::Fn(f)
+        }
+        ImplItem::Const(mut c) => {
+            c.expr = dummy_body;
+            ImplItem::Const(c)
+        }
+        ImplItem::Type(mut t) if mentions(t.ty.to_token_stream(), undeclared) => {
+            t.ty = Type::Tuple(TypeTuple {
+                paren_token: Paren::default(),
+                elems: Punctuated::new(),
+            });
+            ImplItem::Type(t)
+        }
+        other => other,
+    }
+}
+
+/// The bounds of an `impl Trait` return type
+fn impl_trait_bounds(sig: &Signature) -> Option<&Punctuated<TypeParamBound, Plus>> {
+    match &sig.output {
+        ReturnType::Type(_, t) => match &**t {
+            Type::ImplTrait(t) => Some(&t.bounds),
+            _ => None,
+        },
+        ReturnType::Default => None,
+    }
+}
+
+/// Returns `impl Trait`, but not `impl Future`
+fn returns_opaque(sig: &Signature) -> bool {
+    impl_trait_bounds(sig).is_some_and(|bounds| {
+        !bounds.iter().any(|bound| match bound {
+            TypeParamBound::Trait(t) => t
+                .path
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident == "Future"),
+            _ => false,
+        })
+    })
+}
+
+/// The arguments are only passed on, so they don't need to be mutable (`mut self` -> `self`)
+pub fn immutable_args(sig: &mut Signature) {
+    for arg in &mut sig.inputs {
+        match arg {
+            FnArg::Receiver(receiver) if receiver.reference.is_none() => receiver.mutability = None,
+            FnArg::Typed(PatType { pat, .. }) => {
+                if let Pat::Ident(pat) = &mut **pat {
+                    pat.mutability = None
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Without the bound of [`self_bound`], `Self: Trait` would keep the associated types from being normalized
+pub fn dummy_predicates(
+    predicates: impl IntoIterator<Item = WherePredicate>,
+    trait_: &Path,
+) -> Vec<WherePredicate> {
+    let trait_ = &trait_.segments.last().expect("paths are never empty").ident;
+    predicates
+        .into_iter()
+        .filter_map(|pred| match pred {
+            WherePredicate::Type(mut pred)
+                if matches!(&pred.bounded_ty, Type::Path(p) if p.path.is_ident("Self")) =>
+            {
+                pred.bounds = pred
+                    .bounds
+                    .into_iter()
+                    .filter(|bound| {
+                        !matches!(bound, TypeParamBound::Trait(t)
+                            if t.path.segments.last().is_some_and(|seg| &seg.ident == trait_))
+                    })
+                    .collect();
+                (!pred.bounds.is_empty()).then_some(WherePredicate::Type(pred))
+            }
+            other => Some(other),
+        })
+        .collect()
+}
+
+/// Undeclared generics given to the extended impl become `()`
+fn replace_idents(tokens: TokenStream, idents: &[Ident]) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(i) if idents.contains(&i) => quote!(()),
+            TokenTree::Group(g) => {
+                let mut new = Group::new(g.delimiter(), replace_idents(g.stream(), idents));
+                new.set_span(g.span());
+                TokenTree::Group(new).into()
+            }
+            other => other.into(),
+        })
+        .collect()
+}
+
+fn mentions(tokens: TokenStream, idents: &[Ident]) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(i) => idents.contains(&i),
+        TokenTree::Group(g) => mentions(g.stream(), idents),
+        _ => false,
+    })
+}
+
+/// Dummy is generic, so that it can assume to implement the trait (and therefore its supertraits).
+///
+/// Associated types are fixed in the bound, otherwise they couldn't be normalized.
+/// This does not work for `impl Trait` returns (and async fns), so there is no bound in that case.
+/// Neither does it for traits with lifetimes, as the bound can't be told apart from the impl itself.
+fn self_bound(imp: &ItemImpl, mut trait_: Path) -> Option<WherePredicate> {
+    if imp.items.iter().any(|item| match item {
+        ImplItem::Fn(f) => {
+            f.sig.asyncness.is_some()
+                || matches!(&f.sig.output, ReturnType::Type(_, t) if matches!(**t, Type::ImplTrait(_)))
+        }
+        _ => false,
+    }) || trait_.segments.iter().any(|seg| {
+        matches!(&seg.arguments, PathArguments::AngleBracketed(args)
+            if args.args.iter().any(|arg| matches!(arg, GenericArgument::Lifetime(_))))
+    }) {
+        return None;
+    }
+    let assoc_types: Vec<GenericArgument> = imp
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            ImplItem::Type(t) if t.generics.params.is_empty() => {
+                let ident = &t.ident;
+                let ty = &t.ty;
+                Some(parse_quote! {#ident = #ty})
+            }
+            _ => None,
+        })
+        .collect();
+    if !assoc_types.is_empty() {
+        let last = trait_.segments.last_mut()?;
+        match &mut last.arguments {
+            PathArguments::AngleBracketed(args) => args.args.extend(assoc_types),
+            arguments => {
+                *arguments = PathArguments::AngleBracketed(parse_quote! {<#(#assoc_types),*>})
+            }
+        }
+    }
+    Some(parse_quote! {Self: #trait_})
+}
@@ -2,7 +2,8 @@
 #![doc = include_str!("../README.md")]
 use core::panic;
 
-use quote::{quote, ToTokens};
+use quote::quote;
+use syn::ext::IdentExt;
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::{parse_macro_input, ItemImpl, ItemTrait, Path};
@@ -11,12 +12,52 @@ mod change_self;
 mod dummy;
 mod mac;
 mod transform;
+mod wiring;
+mod with;
 
-struct IdentList(Punctuated<syn::Ident, token::Comma>);
-impl syn::parse::Parse for IdentList {
+/// `name`, `name = Path + Path` or `name = [tokens]`
+struct AttrOption {
+    name: Ident,
+    value: Punctuated<Path, token::Plus>,
+    list: Option<proc_macro2::TokenStream>,
+}
+impl syn::parse::Parse for AttrOption {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        // (`for` is a keyword)
+        let name = Ident::parse_any(input)?;
+        let mut value = Punctuated::new();
+        let mut list = None;
+        if input.peek(token::Eq) {
+            input.parse::<token::Eq>()?;
+            if input.peek(token::Bracket) {
+                let content;
+                let bracket = syn::bracketed!(content in input);
+                if name != "for" {
+                    return Err(syn::Error::new(
+                        bracket.span.join(),
+                        format!("`{name}` doesn't take a list"),
+                    ));
+                }
+                list = Some(content.parse()?);
+            } else {
+                value = Punctuated::parse_separated_nonempty(input)?;
+            }
+        }
+        if name == "for" && list.is_none() {
+            return Err(syn::Error::new(
+                name.span(),
+                "`for` takes a list of types, like `for = [u8, u16]`",
+            ));
+        }
+        Ok(AttrOption { name, value, list })
+    }
+}
+
+struct AttrOptions(Punctuated<AttrOption, token::Comma>);
+impl syn::parse::Parse for AttrOptions {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        Ok(IdentList(
-            input.parse_terminated(syn::Ident::parse, token::Comma)?,
+        Ok(AttrOptions(
+            input.parse_terminated(AttrOption::parse, token::Comma)?,
         ))
     }
 }
@@ -35,8 +76,7 @@ impl syn::parse::Parse for IdentList {
 ///     Self::default()
 ///   }
 ///   fn other(&self) -> String {
-///     // You have to use context here instead of self, because we don't change macro contents
-///     format!("{context:?}")
+///     format!("{self:?}")
 ///   }
 /// }
 /// impl_Impl!(());
@@ -50,17 +90,165 @@ pub fn abstract_impl(
     item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
     let parsed = parse_macro_input!(item as ItemImpl);
-    let IdentList(attrs) = parse_macro_input!(_attr);
+    let AttrOptions(attrs) = parse_macro_input!(_attr);
+    let partial = attrs.iter().any(|attr| attr.name == "partial");
+    let mut bases = attrs
+        .iter()
+        .filter(|attr| attr.name == "extends")
+        .flat_map(|attr| attr.value.iter().cloned());
+    let base = bases.next();
+    if let Some(other) = bases.next() {
+        return syn::Error::new(other.span(), "Only one abstract impl can be extended")
+            .into_compile_error()
+            .into();
+    }
+    let blanket = attrs.iter().any(|attr| attr.name == "blanket");
     let res = match transform::transform(
         parsed,
-        attrs.iter().all(|attr| *attr != "no_dummy"),
-        attrs.iter().all(|attr| *attr != "no_macro"),
-        attrs.iter().any(|attr| *attr == "legacy_order"),
+        transform::Options {
+            // the dummy can't check a partial impl for completeness
+            // (and would overlap with a blanket impl, which checks it instead)
+            use_dummy: !partial && !blanket && attrs.iter().all(|attr| attr.name != "no_dummy"),
+            use_macro: attrs.iter().all(|attr| attr.name != "no_macro"),
+            legacy_order: attrs.iter().any(|attr| attr.name == "legacy_order"),
+            partial,
+            blanket,
+            with: attrs.iter().any(|attr| attr.name == "with"),
+            targets: attrs
+                .iter()
+                .find(|attr| attr.name == "for")
+                .and_then(|attr| attr.list.clone()),
+            parts: attrs
+                .iter()
+                .filter(|attr| attr.name == "compose")
+                .flat_map(|attr| attr.value.iter().cloned())
+                .collect(),
+            base,
+        },
     ) {
         Ok(res) => res,
         Err(e) => return e.into_compile_error().into(),
     };
-    res.to_token_stream().into()
+    quote!(#(#res)*).into()
+}
+
+/// Implement a trait by taking each item from an abstract impl
+///
+/// ```
+/// # use abstract_impl::{abstract_impl, impl_trait};
+/// trait Shape {
+///   const NAME: &'static str;
+///   fn area(&self) -> f32;
+/// }
+/// #[abstract_impl]
+/// impl Square for Shape where Self: AsRef<f32> {
+///   const NAME: &'static str = "square";
+///   fn area(&self) -> f32 {
+///     self.as_ref() * self.as_ref()
+///   }
+/// }
+/// #[abstract_impl]
+/// impl Named for Shape {
+///   const NAME: &'static str = "shape";
+///   fn area(&self) -> f32 {
+///     0.0
+///   }
+/// }
+/// struct Tile(f32);
+/// impl AsRef<f32> for Tile {
+///   fn as_ref(&self) -> &f32 {
+///     &self.0
+///   }
+/// }
+/// impl_trait!(Tile: Shape { NAME: Named, area: Square });
+/// # fn main() {
+/// # assert_eq!("shape", Tile::NAME);
+/// # assert_eq!(4.0, Tile(2.0).area());
+/// # }
+/// ```
+#[proc_macro]
+pub fn impl_trait(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let wiring = parse_macro_input!(item as wiring::Wiring);
+    match wiring::generate_wiring(wiring) {
+        Ok(res) => res.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
+
+/// Implement traits for types by abstract impls, checking that every trait is wired once
+///
+/// ```
+/// # use abstract_impl::{abstract_impl, wiring};
+/// # use std::fmt::Debug;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// #[abstract_impl]
+/// impl ByDebug for Describe where Self: Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// #[derive(Debug)]
+/// struct App;
+/// #[derive(Debug)]
+/// struct Wrapper<T>(T);
+/// wiring! {
+///   App {
+///     Describe => ByDebug,
+///   }
+///   for<T: Debug> Wrapper<T> {
+///     Describe => ByDebug,
+///   }
+/// }
+/// # fn main() {
+/// # assert_eq!("App", App.describe());
+/// # assert_eq!("Wrapper(1)", Wrapper(1).describe());
+/// # }
+/// ```
+#[proc_macro]
+pub fn wiring(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let table = parse_macro_input!(item as wiring::Table);
+    match wiring::generate_table(table) {
+        Ok(res) => res.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
+
+/// Implement the traits of abstract impls for a type, next to its definition
+///
+/// ```
+/// # use abstract_impl::abstract_impl;
+/// # use std::fmt::Debug;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// #[abstract_impl]
+/// impl ByDebug for Describe where Self: Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// #[abstract_impl::wire(ByDebug)]
+/// #[derive(Debug)]
+/// struct Test<T: Debug>(T)
+/// where
+///   T: Clone;
+/// # fn main() {
+/// # assert_eq!("Test(1)", Test(1).describe());
+/// # }
+/// ```
+#[proc_macro_attribute]
+pub fn wire(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let impls = parse_macro_input!(attr with Punctuated::<Path, token::Comma>::parse_terminated);
+    let item = parse_macro_input!(item as syn::DeriveInput);
+    match wiring::generate_type_wiring(impls, item) {
+        Ok(res) => res.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
 }
 
 /// Generates a TyType trait (has type Ty) with a generic TyUsingType<T> impl given a type name Ty.
@@ -380,5 +568,1879 @@ pub fn impl_conversion_with_field(item: proc_macro::TokenStream) -> proc_macro::
 /// impl_TimeUsingType!(<usize> ());
 /// fn main() {}
 /// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// use std::fmt::Write;
+/// trait Describe {
+///   fn describe(&self) -> String;
+///   fn is_empty(&self) -> bool;
+///   fn repeat(&self) -> Vec<Self> where Self: Sized;
+///   fn literals(&self) -> [&'static str; 2];
+/// }
+/// macro_rules! first {
+///   ($first:expr, $($rest:expr),*) => {
+///     $first
+///   };
+/// }
+/// #[abstract_impl]
+/// impl DescribeUsingDebug for Describe where Self: std::fmt::Debug + Default + PartialEq + Clone {
+///   fn describe(&self) -> String {
+///     let mut out = format!("{self:?} {{self}} {}", Self::is_empty(self));
+///     write!(out, " {:?}", self).unwrap();
+///     assert_eq!(self, self, "{self:?}");
+///     assert!(!matches!(Self::is_empty(self), true) || self == &Self::default(), "{self:?}");
+///     out
+///   }
+///   fn is_empty(&self) -> bool {
+///     self == &Self::default()
+///   }
+///   fn repeat(&self) -> Vec<Self> {
+///     vec![self.clone(); 2]
+///   }
+///   // only format strings of format macros capture self
+///   fn literals(&self) -> [&'static str; 2] {
+///     [concat!("{self}", ""), first!("{self}", self)]
+///   }
+/// }
+/// #[derive(Debug, Default, PartialEq, Clone)]
+/// struct Test(u8);
+/// impl_DescribeUsingDebug!(Test);
+/// fn main() {
+///   assert_eq!("Test(1) {self} false Test(1)", Test(1).describe());
+///   assert_eq!(vec![Test(1), Test(1)], Test(1).repeat());
+///   assert_eq!(["{self}", "{self}"], Test(1).literals());
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Sizes {
+///   const MAX: u8;
+///   fn is_empty(&self) -> bool;
+///   fn sizes(&self) -> (bool, Vec<u8>);
+/// }
+/// macro_rules! first {
+///   ($first:expr, $($rest:expr),*) => {
+///     $first
+///   };
+/// }
+/// // arguments of any macro can use the items of the impl
+/// #[abstract_impl]
+/// impl ByLen for Sizes where Self: AsRef<[u8]> {
+///   const MAX: u8 = 2;
+///   fn is_empty(&self) -> bool {
+///     self.as_ref().is_empty()
+///   }
+///   fn sizes(&self) -> (bool, Vec<u8>) {
+///     (first!(Self::is_empty(self), Self::MAX), vec![Self::MAX; 2])
+///   }
+/// }
+/// impl_ByLen!(Vec<u8>);
+/// fn main() {
+///   assert_eq!((true, vec![2, 2]), Vec::new().sizes());
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Limits {
+///   type Id;
+///   const MAX: usize;
+///   const ID: Self::Id;
+///   fn twice() -> usize;
+/// }
+/// trait HasId {
+///   const RAW: u8;
+/// }
+/// #[abstract_impl]
+/// impl LimitsUsingId for Limits where Self: HasId {
+///   type Id = u8;
+///   const MAX: usize = 10;
+///   const ID: Self::Id = <Self as HasId>::RAW + 1;
+///   fn twice() -> usize {
+///     Self::MAX * 2
+///   }
+/// }
+/// trait Size<T> {
+///   const SIZE: usize;
+/// }
+/// #[abstract_impl]
+/// impl SizeOf<T> for Size<T> {
+///   const SIZE: usize = std::mem::size_of::<T>() + std::mem::size_of::<Self>();
+/// }
+/// struct Test;
+/// impl HasId for Test {
+///   const RAW: u8 = 4;
+/// }
+/// impl_LimitsUsingId!(Test);
+/// impl_SizeOf!(<u32> Test);
+/// fn main() {
+///   assert_eq!(<Test as Limits>::MAX, 10);
+///   assert_eq!(<Test as Limits>::ID, 5);
+///   assert_eq!(Test::twice(), 20);
+///   assert_eq!(<Test as Size<u32>>::SIZE, 4);
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Defaults {
+///   const DEFAULTS: &'static Vec<u8>;
+///   const LEN: usize;
+///   fn first(&self) -> Option<u8>;
+/// }
+/// // the consts stay consts (with promotion, in array lengths and patterns)
+/// #[abstract_impl]
+/// impl Empty for Defaults {
+///   const DEFAULTS: &'static Vec<u8> = &Vec::new();
+///   const LEN: usize = 2;
+///   fn first(&self) -> Option<u8> {
+///     Self::DEFAULTS.first().copied()
+///   }
+/// }
+/// struct Test;
+/// impl_Empty!(Test);
+/// fn main() {
+///   assert!(<Test as Defaults>::DEFAULTS.is_empty());
+///   assert_eq!(None, Test.first());
+///   let zeros = [0u8; <Test as Defaults>::LEN];
+///   assert!(matches!(zeros.len(), <Test as Defaults>::LEN));
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// use std::future::Future;
+/// trait Service {
+///   async fn call(&self, req: &str) -> String;
+///   fn ready(&self) -> impl Future<Output = bool> + Send;
+///   async fn name(&self, key: &str) -> &str;
+/// }
+/// trait Named {
+///   fn title(&self) -> String;
+/// }
+/// #[abstract_impl]
+/// impl EchoService for Service where Self: Named + Sync {
+///   async fn call(&self, req: &str) -> String {
+///     format!("{}: {req}", self.title())
+///   }
+///   fn ready(&self) -> impl Future<Output = bool> + Send {
+///     async move { !self.title().is_empty() }
+///   }
+///   async fn name(&self, key: &str) -> &str {
+///     if key.is_empty() { "empty" } else { "key" }
+///   }
+/// }
+/// struct Test;
+/// impl Named for Test {
+///   fn title(&self) -> String {
+///     "test".to_string()
+///   }
+/// }
+/// impl_EchoService!(Test);
+/// fn block_on<F: Future>(f: F) -> F::Output {
+///   use std::task::{Context, Poll, Waker};
+///   let mut f = std::pin::pin!(f);
+///   let mut cx = Context::from_waker(Waker::noop());
+///   loop {
+///     if let Poll::Ready(v) = f.as_mut().poll(&mut cx) {
+///       return v;
+///     }
+///   }
+/// }
+/// fn main() {
+///   assert_eq!("test: hi", block_on(Test.call("hi")));
+///   assert!(block_on(Test.ready()));
+///   assert_eq!("key", block_on(Test.name("key")));
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Base {
+///   fn base(&self) -> u8;
+/// }
+/// trait Sub: Base + Clone {
+///   type Out;
+///   fn sub(&self) -> Self::Out;
+/// }
+/// #[abstract_impl]
+/// impl SubUsingBase for Sub where Self: Base + Clone {
+///   type Out = u8;
+///   fn sub(&self) -> Self::Out {
+///     self.clone().base() + 1
+///   }
+/// }
+/// #[derive(Clone)]
+/// struct Test;
+/// impl Base for Test {
+///   fn base(&self) -> u8 {
+///     1
+///   }
+/// }
+/// impl_SubUsingBase!(Test);
+/// fn main() {
+///   assert_eq!(2, Test.sub());
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Sub: Clone {
+///   fn sub(&self) -> u8;
+///   fn other(&self) -> u8;
+/// }
+/// #[abstract_impl]
+/// impl MissingOther for Sub {
+///   fn sub(&self) -> u8 {
+///     1
+///   }
+/// }
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Container {
+///   type Item;
+///   type Iter<'a>: Iterator<Item = &'a Self::Item> where Self: 'a;
+///   type View<'a>: AsRef<[u8]> where Self: 'a;
+///   type Arr<const N: usize>;
+///   fn iter<'a>(&'a self) -> Self::Iter<'a>;
+///   fn view(&self) -> Self::View<'_>;
+///   fn first(&self) -> Option<&Self::Item>;
+/// }
+/// #[abstract_impl]
+/// impl SliceContainer for Container where Self: AsRef<[u8]> {
+///   type Item = u8;
+///   type Iter<'a> = std::slice::Iter<'a, u8> where Self: 'a;
+///   type View<'a> = &'a Self where Self: 'a;
+///   type Arr<const N: usize> = [Self::Item; N];
+///   fn iter<'a>(&'a self) -> Self::Iter<'a> {
+///     self.as_ref().iter()
+///   }
+///   fn view(&self) -> Self::View<'_> {
+///     self
+///   }
+///   fn first(&self) -> Option<&Self::Item> {
+///     self.as_ref().first()
+///   }
+/// }
+/// struct Test(Vec<u8>);
+/// impl AsRef<[u8]> for Test {
+///   fn as_ref(&self) -> &[u8] {
+///     &self.0
+///   }
+/// }
+/// impl_SliceContainer!(Test);
+/// fn main() {
+///   let t = Test(vec![1, 2]);
+///   assert_eq!(3, t.iter().sum::<u8>());
+///   assert_eq!(&[1, 2], t.view().as_ref().as_ref());
+///   assert_eq!(Some(&1), t.first());
+///   let arr: <Test as Container>::Arr<2> = [1, 2];
+///   assert_eq!(2, arr.len());
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Source<'src> {
+///   fn source(&self) -> &'src str;
+/// }
+/// trait Pair<'a, T> {
+///   type Left;
+///   const NAME: &'static str;
+///   fn pair(&self) -> (Self::Left, T);
+/// }
+/// #[abstract_impl]
+/// impl PairWithDefault<'a, T> for Pair<'a, T> where Self: Source<'a>, T: Default {
+///   type Left = &'a str;
+///   const NAME: &'static str = "pair";
+///   fn pair(&self) -> (Self::Left, T) {
+///     (self.source(), T::default())
+///   }
+/// }
+/// struct Lexer<'a>(&'a str);
+/// impl<'a> Source<'a> for Lexer<'a> {
+///   fn source(&self) -> &'a str {
+///     self.0
+///   }
+/// }
+/// impl_PairWithDefault!(<'b, Option<&'b u8>> Lexer<'b>);
+/// fn main() {
+///   let l = Lexer("hello");
+///   assert_eq!(("hello", None), l.pair());
+///   assert_eq!("pair", <Lexer as Pair<Option<&u8>>>::NAME);
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// use std::fmt::Debug;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// #[abstract_impl]
+/// impl DebugToString for Describe where Self: Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// trait HasType<T> {
+///   fn get_type(&self) -> T;
+/// }
+/// trait FormatType<T> {
+///   const FIRST: Option<T>;
+///   fn format_type(&self) -> String;
+/// }
+/// #[abstract_impl]
+/// impl FormatField<T> for FormatType<T> where Self: HasType<T>, T: ToString {
+///   const FIRST: Option<T> = None;
+///   fn format_type(&self) -> String {
+///     self.get_type().to_string()
+///   }
+/// }
+/// #[derive(Debug)]
+/// struct Wrapper<T, const N: usize>([T; N]);
+/// impl<T: Clone, const N: usize> HasType<T> for Wrapper<T, N> {
+///   fn get_type(&self) -> T {
+///     self.0[0].clone()
+///   }
+/// }
+/// impl_FormatField!(<T> for<T: Clone + ToString, const N: usize> Wrapper<T, N>);
+/// #[derive(Debug)]
+/// struct Nested<T>(T);
+/// impl_DebugToString!(for<T: Into<Vec<Option<u8>>> + Debug> Nested<T> where T: Clone);
+/// impl_DebugToString!(for<'a> &'a Wrapper<u8, 1>);
+/// struct Small(u8);
+/// impl HasType<u8> for Small {
+///   fn get_type(&self) -> u8 {
+///     self.0
+///   }
+/// }
+/// impl_FormatField!(<u8> Small);
+/// struct Big(u16);
+/// impl HasType<u16> for Big {
+///   fn get_type(&self) -> u16 {
+///     self.0
+///   }
+/// }
+/// impl_FormatField!(<u16> Big);
+/// fn main() {
+///   assert_eq!("3", Wrapper([3u8]).format_type());
+///   assert_eq!(None, <Wrapper<u8, 1> as FormatType<u8>>::FIRST);
+///   assert_eq!("Nested([Some(1)])", Nested(vec![Some(1u8)]).describe());
+///   assert_eq!("Wrapper([1])", (&Wrapper([1u8])).describe());
+///   assert_eq!("4", Small(4).format_type());
+///   assert_eq!("5", Big(5).format_type());
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Named<T> {
+///   const ID: u8;
+///   fn name(&self) -> T;
+///   fn twice(&self) -> (T, T);
+/// }
+/// #[abstract_impl(partial)]
+/// impl Twice<T> for Named<T> where T: Clone {
+///   fn twice(&self) -> (T, T) {
+///     (self.name(), self.name())
+///   }
+/// }
+/// struct Test;
+/// impl_Twice!(<u8> Test {
+///   const ID: u8 = 1;
+///   fn name(&self) -> u8 {
+///     3
+///   }
+/// });
+/// struct Wrapper<T>(T);
+/// impl_Twice!(<T> for<T: Clone> Wrapper<T> where T: Copy {
+///   const ID: u8 = 2;
+///   fn name(&self) -> T {
+///     self.0
+///   }
+/// });
+/// fn main() {
+///   assert_eq!((3, 3), Test.twice());
+///   assert_eq!((5, 5), Wrapper(5).twice());
+///   assert_eq!(3, Test::ID + Wrapper::<u8>::ID);
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Greet {
+///   fn name(&self) -> &str;
+///   fn greet(&self) -> String;
+/// }
+/// #[abstract_impl(partial)]
+/// impl GreetByName for Greet {
+///   fn greet(&self) -> String {
+///     format!("Hello {}", self.name())
+///   }
+/// }
+/// struct Twice;
+/// impl_GreetByName!(Twice {
+///   fn name(&self) -> &str {
+///     "twice"
+///   }
+///   fn greet(&self) -> String {
+///     String::new()
+///   }
+/// });
+/// fn main() {}
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Greet {
+///   fn name(&self) -> &str;
+///   fn greet(&self) -> String;
+/// }
+/// #[abstract_impl(partial)]
+/// impl GreetByName for Greet {
+///   fn greet(&self) -> String {
+///     format!("Hello {}", self.name())
+///   }
+/// }
+/// struct Missing;
+/// impl_GreetByName!(Missing);
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::{abstract_impl, impl_trait};
+/// trait Geometry {
+///   const NAME: &'static str;
+///   type Unit;
+///   fn area(&self) -> f32;
+///   fn perimeter(&self) -> f32;
+/// }
+/// trait Edges {
+///   fn edges(&self) -> Vec<f32>;
+/// }
+/// #[abstract_impl]
+/// impl Nothing for Geometry {
+///   const NAME: &'static str = "nothing";
+///   type Unit = ();
+///   fn area(&self) -> f32 {
+///     0.0
+///   }
+///   fn perimeter(&self) -> f32 {
+///     0.0
+///   }
+/// }
+/// #[abstract_impl(partial)]
+/// impl PerimeterFromEdges for Geometry where Self: Edges {
+///   fn perimeter(&self) -> f32 {
+///     self.edges().iter().sum()
+///   }
+/// }
+/// #[abstract_impl(partial)]
+/// impl UnitOf<T> for Geometry {
+///   type Unit = T;
+/// }
+/// struct Line<T>(T);
+/// impl<T> Edges for Line<T> {
+///   fn edges(&self) -> Vec<f32> {
+///     vec![1.0, 2.0]
+///   }
+/// }
+/// impl_trait!(for<T: Clone> Line<T>: Geometry where T: Copy {
+///   NAME: Nothing,
+///   Unit: UnitOf<T>,
+///   area: Nothing,
+///   perimeter: PerimeterFromEdges,
+/// });
+/// fn main() {
+///   assert_eq!(3.0, Line(1u8).perimeter());
+///   assert_eq!("nothing", <Line<u8> as Geometry>::NAME);
+///   let unit: <Line<u8> as Geometry>::Unit = 1u8;
+///   assert_eq!(1, unit);
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::{abstract_impl, impl_trait};
+/// trait Geometry {
+///   fn area(&self) -> f32;
+/// }
+/// #[abstract_impl]
+/// impl Nothing for Geometry {
+///   fn area(&self) -> f32 {
+///     0.0
+///   }
+/// }
+/// struct Point;
+/// impl_trait!(Point: Geometry { area: Nothing, area: Nothing });
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// use std::fmt::Debug;
+/// trait Store {
+///   type Key;
+///   fn load(&self) -> Vec<u8>;
+///   fn format(&self) -> String;
+///   fn name(&self) -> &'static str;
+/// }
+/// #[abstract_impl(partial)]
+/// impl StoragePart for Store where Self: AsRef<Vec<u8>> {
+///   type Key = u8;
+///   fn load(&self) -> Vec<u8> {
+///     self.as_ref().clone()
+///   }
+/// }
+/// #[abstract_impl(partial)]
+/// impl FormatPart<T> for Store where Self: Debug, T: Default + Debug {
+///   fn format(&self) -> String {
+///     format!("{self:?} {:?}", T::default())
+///   }
+/// }
+/// #[abstract_impl(compose = StoragePart + FormatPart<T>)]
+/// impl FullWith<T> for Store {
+///   fn name(&self) -> &'static str {
+///     "full"
+///   }
+/// }
+/// #[abstract_impl(compose = FullWith<u8>)]
+/// impl Nested for Store {}
+/// #[derive(Debug)]
+/// struct Db<T>(Vec<u8>, T);
+/// impl<T> AsRef<Vec<u8>> for Db<T> {
+///   fn as_ref(&self) -> &Vec<u8> {
+///     &self.0
+///   }
+/// }
+/// impl_FullWith!(<u16> for<T: Debug> Db<T>);
+/// #[derive(Debug)]
+/// struct Other(Vec<u8>);
+/// impl AsRef<Vec<u8>> for Other {
+///   fn as_ref(&self) -> &Vec<u8> {
+///     &self.0
+///   }
+/// }
+/// impl_Nested!(Other);
+/// fn main() {
+///   assert_eq!("Db([], 'x') 0", Db(vec![], 'x').format());
+///   assert_eq!("full", Db(vec![], 'x').name());
+///   assert_eq!(vec![1], Other(vec![1]).load());
+///   let key: <Other as Store>::Key = 1u8;
+///   assert_eq!(1, key);
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Store {
+///   fn load(&self) -> Vec<u8>;
+///   fn format(&self) -> String;
+/// }
+/// #[abstract_impl(partial)]
+/// impl StoragePart for Store where Self: AsRef<Vec<u8>> {
+///   fn load(&self) -> Vec<u8> {
+///     self.as_ref().clone()
+///   }
+/// }
+/// #[abstract_impl(compose = StoragePart)]
+/// impl Incomplete for Store {}
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// use std::fmt::Debug;
+/// trait Greet {
+///   type Name;
+///   const PREFIX: &'static str;
+///   fn name(&self) -> Self::Name;
+///   fn greet(&self) -> String;
+///   fn shout(&self) -> String;
+/// }
+/// #[abstract_impl]
+/// impl Plain for Greet where Self: Debug {
+///   type Name = String;
+///   const PREFIX: &'static str = "Hello";
+///   fn name(&self) -> String {
+///     format!("{self:?}")
+///   }
+///   fn greet(&self) -> String {
+///     format!("{} {}", Self::PREFIX, Self::name(self))
+///   }
+///   fn shout(&self) -> String {
+///     Self::greet(self).to_uppercase()
+///   }
+/// }
+/// #[abstract_impl(extends = Plain)]
+/// impl Polite for Greet where Self: Clone {
+///   const PREFIX: &'static str = "Good day";
+///   fn shout(&self) -> String {
+///     format!("{}!", self.clone().greet())
+///   }
+/// }
+/// #[abstract_impl(extends = Polite)]
+/// impl Quiet for Greet {
+///   fn greet(&self) -> String {
+///     format!("psst, {}", Self::PREFIX.to_lowercase())
+///   }
+/// }
+/// #[abstract_impl]
+/// impl Converted<T> for Greet where Self: Into<T> + Clone, T: Debug {
+///   type Name = String;
+///   const PREFIX: &'static str = "Hi";
+///   fn name(&self) -> String {
+///     format!("{:?}", Into::<T>::into(self.clone()))
+///   }
+///   fn greet(&self) -> String {
+///     format!("{} {:?}", Self::PREFIX, Into::<T>::into(self.clone()))
+///   }
+///   fn shout(&self) -> String {
+///     String::from("HI")
+///   }
+/// }
+/// #[abstract_impl(extends = Converted<T>)]
+/// impl Loud<T> for Greet {
+///   fn shout(&self) -> String {
+///     format!("{}!!!", self.greet())
+///   }
+/// }
+/// #[derive(Debug, Clone)]
+/// struct A;
+/// #[derive(Debug, Clone)]
+/// struct B;
+/// #[derive(Debug, Clone)]
+/// struct Num(u8);
+/// impl From<Num> for u32 {
+///   fn from(num: Num) -> u32 {
+///     num.0 as u32 * 2
+///   }
+/// }
+/// impl_Polite!(A);
+/// impl_Quiet!(B);
+/// impl_Loud!(<u32> Num);
+/// impl_Polite!(for<T: Debug + Clone> Vec<T>);
+/// fn main() {
+///   // the inherited items use the overriding items
+///   assert_eq!("Good day A", A.greet());
+///   assert_eq!("Good day A!", A.shout());
+///   assert_eq!("A", Polite::name(&A));
+///   assert_eq!("psst, good day!", B.shout());
+///   assert_eq!("8", Num(4).name());
+///   assert_eq!("Hi 8!!!", Num(4).shout());
+///   assert_eq!("Good day [1]!", vec![1].shout());
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Print {
+///   type Error;
+///   fn check(&self) -> Result<(), Self::Error>;
+///   fn print(&self) -> Result<String, Self::Error>;
+/// }
+/// #[abstract_impl]
+/// impl PrintUnitErr for Print where Self: AsRef<str> {
+///   type Error = ();
+///   fn check(&self) -> Result<(), ()> {
+///     if self.as_ref().is_empty() { Err(()) } else { Ok(()) }
+///   }
+///   fn print(&self) -> Result<String, Self::Error> {
+///     Self::check(self)?;
+///     Ok(self.as_ref().to_owned())
+///   }
+/// }
+/// #[abstract_impl(extends = PrintUnitErr)]
+/// impl PrintStringErr for Print where Self: AsRef<str> {
+///   type Error = String;
+///   fn check(&self) -> Result<(), String> {
+///     if self.as_ref().is_empty() { Err("empty".into()) } else { Ok(()) }
+///   }
+/// }
+/// struct Text(&'static str);
+/// impl AsRef<str> for Text {
+///   fn as_ref(&self) -> &str {
+///     self.0
+///   }
+/// }
+/// impl_PrintStringErr!(Text);
+/// fn main() {
+///   assert_eq!(Ok(String::from("hi")), Text("hi").print());
+///   assert_eq!(Err(String::from("empty")), Text("").print());
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Store {
+///   fn load(&self) -> Vec<u8>;
+///   fn format(&self) -> String;
+/// }
+/// #[abstract_impl(partial)]
+/// impl StoragePart for Store where Self: AsRef<Vec<u8>> {
+///   fn load(&self) -> Vec<u8> {
+///     self.as_ref().clone()
+///   }
+/// }
+/// #[abstract_impl(partial)]
+/// impl FormatPart for Store where Self: std::fmt::Debug {
+///   fn format(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// #[abstract_impl(compose = StoragePart + FormatPart)]
+/// impl Full for Store {}
+/// #[abstract_impl(extends = Full)]
+/// impl Empty for Store {
+///   fn load(&self) -> Vec<u8> {
+///     vec![]
+///   }
+/// }
+/// #[derive(Debug)]
+/// struct Db(Vec<u8>);
+/// impl AsRef<Vec<u8>> for Db {
+///   fn as_ref(&self) -> &Vec<u8> {
+///     &self.0
+///   }
+/// }
+/// impl_Empty!(Db);
+/// fn main() {
+///   assert_eq!(Vec::<u8>::new(), Db(vec![1]).load());
+///   assert_eq!("Db([1])", Db(vec![1]).format());
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Greet {
+///   fn greet(&self) -> String;
+///   fn shout(&self) -> String;
+/// }
+/// #[abstract_impl]
+/// impl Plain for Greet where Self: std::fmt::Debug {
+///   fn greet(&self) -> String {
+///     format!("{self:?}")
+///   }
+///   fn shout(&self) -> String {
+///     format!("{self:?}!")
+///   }
+/// }
+/// #[abstract_impl(extends = Plain)]
+/// impl Silent for Greet {
+///   fn shout(&self) -> String {
+///     String::new()
+///   }
+/// }
+/// // the bounds of the base apply as well
+/// struct NoDebug;
+/// impl_Silent!(NoDebug);
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// use std::fmt::{self, Debug, Display, Formatter};
+/// trait HasKey {
+///   fn key(&self) -> u32;
+/// }
+/// trait Named<T> {
+///   fn name(&self) -> T;
+/// }
+/// #[abstract_impl]
+/// impl Shown<T> for (Display, Debug, Named<T>, std::error::Error) where Self: HasKey, T: From<u32> {
+///   #[of(Display)]
+///   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+///     write!(f, "#{}", self.key())
+///   }
+///   #[of(Debug)]
+///   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+///     write!(f, "Key({})", self.key())
+///   }
+///   #[of(Named<T>)]
+///   fn name(&self) -> T {
+///     T::from(self.key())
+///   }
+/// }
+/// struct Id<U>(U);
+/// impl<U> HasKey for Id<U> {
+///   fn key(&self) -> u32 {
+///     5
+///   }
+/// }
+/// impl_Shown!(<u64> for<U> Id<U>);
+/// fn main() {
+///   assert_eq!("#5 Key(5) 5", format!("{} {:?} {}", Id(()), Id(()), Id(()).name()));
+///   let error: Box<dyn std::error::Error> = Box::new(Id(()));
+///   assert_eq!("#5", error.to_string());
+///   // the module has both `fmt`s, prefixed with the trait
+///   struct ViaModule(Id<()>);
+///   impl Display for ViaModule {
+///     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+///       Shown::Debug_fmt::<_, u64>(&self.0, f)
+///     }
+///   }
+///   assert_eq!("Key(5)", ViaModule(Id(())).to_string());
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait First {
+///   fn first(&self);
+/// }
+/// trait Second {
+///   fn second(&self);
+/// }
+/// // the traits of the items are unknown
+/// #[abstract_impl]
+/// impl Both for (First, Second) {
+///   fn first(&self) {}
+///   fn second(&self) {}
+/// }
+/// fn main() {}
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Second {
+///   fn second(&self);
+/// }
+/// // `eq` isn't assigned by its name, `Second` could have it as well
+/// #[abstract_impl]
+/// impl Both for (PartialEq, Second) {
+///   fn eq(&self, _other: &Self) -> bool {
+///     true
+///   }
+///   #[of(Second)]
+///   fn second(&self) {}
+/// }
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Convert<T> {
+///   const NAME: &'static str;
+///   fn convert(&self) -> T;
+/// }
+/// #[abstract_impl(blanket, no_macro)]
+/// impl ByInto<T> for Convert<T> where Self: Clone + Into<T> {
+///   const NAME: &'static str = "into";
+///   fn convert(&self) -> T {
+///     self.clone().into()
+///   }
+/// }
+/// trait Source<'a> {
+///   fn source(&self) -> &'a str;
+/// }
+/// trait First<'a> {
+///   fn first(&self) -> &'a str;
+/// }
+/// #[abstract_impl(blanket)]
+/// impl FirstWord<'a> for First<'a> where Self: Source<'a> {
+///   fn first(&self) -> &'a str {
+///     self.source().split(' ').next().unwrap_or_default()
+///   }
+/// }
+/// struct Text(&'static str);
+/// impl Source<'static> for Text {
+///   fn source(&self) -> &'static str {
+///     self.0
+///   }
+/// }
+/// mod nested {
+///   use abstract_impl::abstract_impl;
+///   pub trait Hello {
+///     fn hello(&self) -> String;
+///   }
+///   #[abstract_impl(blanket)]
+///   impl Plain for Hello {
+///     fn hello(&self) -> String {
+///       String::from("hi")
+///     }
+///   }
+/// }
+/// use nested::Hello;
+/// fn main() {
+///   assert_eq!(5u64, Convert::<u64>::convert(&5u8));
+///   assert_eq!("into", <u8 as Convert<u64>>::NAME);
+///   assert_eq!("a", Text("a b").first());
+///   assert_eq!("hi", ().hello());
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// #[abstract_impl(blanket, no_macro)]
+/// impl ByDebug for Describe where Self: std::fmt::Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// // the impl macro is only used for the blanket impl, so there is none to call
+/// // (an empty list wouldn't implement anything)
+/// impl_ByDebug!();
+/// fn main() {}
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// // `T` can't be inferred
+/// #[abstract_impl(blanket)]
+/// impl WithDefault<T> for Describe where T: Default + std::fmt::Debug {
+///   fn describe(&self) -> String {
+///     format!("{:?}", T::default())
+///   }
+/// }
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Counter {
+///   const START: u32;
+///   type Count;
+///   fn fresh() -> Self;
+///   fn bump(&mut self);
+///   fn merged(self, other: Self) -> Self;
+///   fn count(&self) -> Self::Count;
+/// }
+/// #[abstract_impl(with)]
+/// impl Counting for Counter where Self: Default + Copy + std::ops::AddAssign<u32> + Into<u64> {
+///   const START: u32 = 0;
+///   type Count = u64;
+///   fn fresh() -> Self {
+///     Self::default()
+///   }
+///   fn bump(&mut self) {
+///     *self += 1;
+///   }
+///   fn merged(self, other: Self) -> Self {
+///     let mut merged = self;
+///     for _ in 0..other.into() {
+///       merged += 1;
+///     }
+///     merged
+///   }
+///   fn count(&self) -> u64 {
+///     (*self).into()
+///   }
+/// }
+/// trait Named<T> {
+///   fn name(&self) -> T;
+/// }
+/// #[abstract_impl(with)]
+/// impl ByString<T> for Named<T> where Self: ToString, T: From<String> {
+///   fn name(&self) -> T {
+///     T::from(self.to_string())
+///   }
+/// }
+/// #[abstract_impl(with)]
+/// impl ByDebug for (PartialEq, std::fmt::Display) where Self: std::fmt::Debug {
+///   #[of(PartialEq)]
+///   fn eq(&self, other: &Self) -> bool {
+///     format!("{self:?}") == format!("{other:?}")
+///   }
+///   #[of(std::fmt::Display)]
+///   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///     write!(f, "{self:?}")
+///   }
+/// }
+/// fn main() {
+///   let mut n = 3u32;
+///   Counting::With::from_mut(&mut n).bump();
+///   assert_eq!(4, n);
+///   let merged = Counting::With(2u32).merged(Counting::With(3));
+///   assert_eq!(5, merged.0);
+///   assert_eq!(5, merged.count());
+///   assert_eq!(0, <Counting::With<u32> as Counter>::fresh().0);
+///   assert_eq!(0, <Counting::With<u32> as Counter>::START);
+///   let name: String = ByString::With(5).name();
+///   assert_eq!("5", name);
+///   assert!(ByDebug::With(Some(1)) == ByDebug::With(Some(1)));
+///   assert_eq!("Some(1)", ByDebug::With(Some(1)).to_string());
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// #[abstract_impl(with)]
+/// impl ByDebug for Describe where Self: std::fmt::Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// struct NoDebug;
+/// fn main() {
+///   ByDebug::With(NoDebug).describe();
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// // `With` can't know the items, which aren't given
+/// #[abstract_impl(partial, with)]
+/// impl Empty for Describe {}
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Parse: Sized {
+///   fn parse(text: &str) -> Option<Self>;
+///   fn at_most(self, max: u32) -> Result<Self, String>;
+///   fn bigger(self, other: Self) -> Self;
+/// }
+/// #[abstract_impl(with)]
+/// impl ByFromStr for Parse where Self: std::str::FromStr + Into<u32> + Copy {
+///   fn parse(text: &str) -> Option<Self> {
+///     if text.is_empty() {
+///       return None;
+///     }
+///     text.parse().ok()
+///   }
+///   fn at_most(self, max: u32) -> Result<Self, String> {
+///     if self.into() > max {
+///       return Err(format!("{} > {max}", self.into()));
+///     }
+///     Ok(self)
+///   }
+///   fn bigger(self, other: Self) -> Self {
+///     if self.into() > other.into() {
+///       return self;
+///     }
+///     other
+///   }
+/// }
+/// use ByFromStr::With;
+/// fn main() {
+///   assert_eq!(Some(5), With::<u8>::parse("5").map(|parsed| parsed.0));
+///   assert!(With::<u8>::parse("").is_none());
+///   assert_eq!(Ok(5), With(5u8).at_most(6).map(|checked| checked.0));
+///   assert_eq!(Err("7 > 6".to_string()), With(7u8).at_most(6).map(|checked| checked.0));
+///   assert_eq!(7, With(7u8).bigger(With(3)).0);
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Parse: Sized {
+///   fn all(text: &str) -> Vec<Self>;
+/// }
+/// // `With` can't wrap the values of a `Vec` again
+/// #[abstract_impl(with)]
+/// impl ByFromStr for Parse where Self: std::str::FromStr {
+///   fn all(text: &str) -> Vec<Self> {
+///     text.split(' ').filter_map(|word| word.parse().ok()).collect()
+///   }
+/// }
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// use std::fmt::Debug;
+/// #[abstract_impl]
+/// impl Describe where Self: Debug + Clone {
+///   /// Describes it
+///   fn describe(&self) -> String {
+///     format!("<{:?}>", self.clone())
+///   }
+///   fn copied(&self) -> Self {
+///     self.clone()
+///   }
+///   fn tagged(self, tag: &str, times: usize) -> String {
+///     tag.repeat(times) + &Self::describe(&self)
+///   }
+///   const KIND: &'static str = "described";
+///   type Pair = (Self, Self);
+/// }
+/// #[abstract_impl(blanket)]
+/// impl Shout<T> where Self: ToString, T: From<String> {
+///   fn shout(&self) -> T {
+///     T::from(self.to_string().to_uppercase())
+///   }
+/// }
+/// #[abstract_impl]
+/// impl<const N: usize> Repeat<N> where Self: ToString {
+///   fn repeat(&self) -> String {
+///     self.to_string().repeat(N)
+///   }
+/// }
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct Test;
+/// impl_Describe!(Test);
+/// impl_Repeat!(<2> u8);
+/// fn main() {
+///   assert_eq!("<Test>", Test.describe());
+///   assert_eq!(Test, Test.copied());
+///   assert_eq!("xx<Test>", Test.tagged("x", 2));
+///   assert_eq!("described", Test::KIND);
+///   let pair: <Test as DescribeExt>::Pair = (Test, Test);
+///   assert_eq!((Test, Test), pair);
+///   let shout: String = "abc".shout();
+///   assert_eq!("ABC", shout);
+///   assert_eq!("11", 1u8.repeat());
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// #[abstract_impl]
+/// impl Describe where Self: std::fmt::Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// struct NoDebug;
+/// impl_Describe!(NoDebug);
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::{abstract_impl, wire};
+/// use std::fmt::Debug;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// trait Named<T> {
+///   fn name(&self) -> T;
+/// }
+/// #[abstract_impl]
+/// impl ByDebug for Describe where Self: Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// #[abstract_impl]
+/// impl ByDescription<T> for Named<T> where Self: Describe, T: From<String> {
+///   fn name(&self) -> T {
+///     T::from(self.describe())
+///   }
+/// }
+/// #[wire(ByDebug, ByDescription<String>)]
+/// #[derive(Debug)]
+/// struct Plain;
+/// #[wire(ByDebug)]
+/// #[derive(Debug)]
+/// enum Slice<'a, T: Debug = u8, const N: usize = 2>
+/// where
+///   T: Clone,
+/// {
+///   Full(&'a [T; N]),
+///   Empty,
+/// }
+/// fn main() {
+///   assert_eq!("Plain", Plain.describe());
+///   assert_eq!("Plain", Plain.name());
+///   assert_eq!("Full([1, 2])", Slice::Full(&[1u8, 2]).describe());
+///   assert_eq!("Empty", Slice::<u8, 2>::Empty.describe());
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::{abstract_impl, wire};
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// #[abstract_impl]
+/// impl ByDebug for Describe where Self: std::fmt::Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// #[wire(ByDebug)]
+/// struct NoDebug;
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::{abstract_impl, wiring};
+/// use std::fmt::Debug;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// trait Named<T> {
+///   fn name(&self) -> T;
+/// }
+/// trait First<'a> {
+///   fn first(&'a self) -> &'a str;
+/// }
+/// #[abstract_impl]
+/// impl ByDebug for Describe where Self: Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// #[abstract_impl]
+/// impl ByDescription<T> for Named<T> where Self: Describe, T: From<String> {
+///   fn name(&self) -> T {
+///     T::from(self.describe())
+///   }
+/// }
+/// #[abstract_impl]
+/// impl FirstWord<'a> for First<'a> where Self: AsRef<str> {
+///   fn first(&'a self) -> &'a str {
+///     self.as_ref().split(' ').next().unwrap_or_default()
+///   }
+/// }
+/// #[abstract_impl]
+/// impl ByDescriptions for (PartialEq, Eq) where Self: Describe {
+///   #[of(PartialEq)]
+///   fn eq(&self, other: &Self) -> bool {
+///     self.describe() == other.describe()
+///   }
+/// }
+/// #[derive(Debug)]
+/// struct App;
+/// #[derive(Debug)]
+/// struct Array<T, const N: usize>([T; N]);
+/// struct Text(String);
+/// impl AsRef<str> for Text {
+///   fn as_ref(&self) -> &str {
+///     &self.0
+///   }
+/// }
+/// wiring! {
+///   App {
+///     Describe => ByDebug,
+///     Named<String> => ByDescription<String>,
+///     PartialEq => ByDescriptions,
+///     Eq => ByDescriptions,
+///   }
+///   for<T: Debug, const N: usize> Array<T, N> where T: Clone {
+///     Describe => ByDebug,
+///     Named<T> => ByDescription<T>,
+///   }
+///   for<'a> Text {
+///     First<'a> => FirstWord<'a>,
+///   }
+/// }
+/// fn main() {
+///   let name: String = App.name();
+///   assert_eq!("App", name);
+///   assert!(App == App);
+///   assert_eq!("Array([1])", Array([1u8]).describe());
+///   let name: String = Array([String::new()]).name();
+///   assert_eq!("Array([\"\"])", name);
+///   assert_eq!("a", Text(String::from("a b")).first());
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::{abstract_impl, wiring};
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// trait Print {
+///   fn print(&self);
+/// }
+/// #[abstract_impl]
+/// impl ByDebug for Describe where Self: std::fmt::Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// #[derive(Debug)]
+/// struct App;
+/// // `ByDebug` doesn't implement `Print`
+/// wiring! {
+///   App {
+///     Print => ByDebug,
+///   }
+/// }
+/// fn main() {}
+/// ```
+/// ```compile_fail
+/// use abstract_impl::{abstract_impl, wiring};
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// #[abstract_impl]
+/// impl ByDebug for Describe where Self: std::fmt::Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// #[abstract_impl]
+/// impl Constant for Describe {
+///   fn describe(&self) -> String {
+///     String::new()
+///   }
+/// }
+/// #[derive(Debug)]
+/// struct App;
+/// // `Describe` is wired twice
+/// wiring! {
+///   App {
+///     Describe => ByDebug,
+///     Describe => Constant,
+///   }
+/// }
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// use std::fmt::Debug;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// trait Named<T> {
+///   fn name(&self) -> T;
+/// }
+/// trait Pair {
+///   fn first(&self) -> u8;
+///   fn second(&self) -> u8;
+/// }
+/// #[derive(Debug)]
+/// struct A;
+/// #[derive(Debug)]
+/// struct B;
+/// #[derive(Debug)]
+/// struct Wrapper<T>(T);
+/// #[abstract_impl]
+/// impl ByDebug for Describe where Self: Debug {
+///   fn describe(&self) -> String {
+///     format!("{self:?}")
+///   }
+/// }
+/// impl_ByDebug!(A, B, for<T: Debug> Wrapper<T>);
+/// #[abstract_impl]
+/// impl ByDescription<T> for Named<T> where Self: Describe, T: From<String> {
+///   fn name(&self) -> T {
+///     T::from(self.describe())
+///   }
+/// }
+/// impl_ByDescription!(<String> A, B, <Box<str>> A, B,);
+/// #[abstract_impl(partial)]
+/// impl FirstZero for Pair {
+///   fn first(&self) -> u8 {
+///     0
+///   }
+/// }
+/// impl_FirstZero!(A { fn second(&self) -> u8 { 1 } }, B { fn second(&self) -> u8 { 2 } });
+/// mod nested {
+///   use abstract_impl::abstract_impl;
+///   pub struct C;
+///   pub trait Hello {
+///     fn hello(&self) -> &'static str;
+///   }
+///   #[abstract_impl(no_macro, for = [C, u8])]
+///   impl Hi for Hello {
+///     fn hello(&self) -> &'static str {
+///       "hi"
+///     }
+///   }
+/// }
+/// #[abstract_impl(for = [<u16> A, B, <u32> B])]
+/// impl Sixteen<T> for Named<T> where T: From<u16> {
+///   fn name(&self) -> T {
+///     T::from(16)
+///   }
+/// }
+/// use nested::Hello;
+/// fn main() {
+///   assert_eq!("Wrapper(1)", Wrapper(1).describe());
+///   let (a, b): (String, Box<str>) = (A.name(), B.name());
+///   assert_eq!(("A", "B"), (a.as_str(), &*b));
+///   assert_eq!((0, 2), (B.first(), B.second()));
+///   assert_eq!("hi", nested::C.hello());
+///   assert_eq!("hi", 1u8.hello());
+///   assert_eq!(16u16, Named::<u16>::name(&A));
+///   assert_eq!(16u32, Named::<u32>::name(&B));
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Hello {
+///   fn hello(&self) -> &'static str;
+/// }
+/// #[abstract_impl(no_macro, for = [u8])]
+/// impl Hi for Hello {
+///   fn hello(&self) -> &'static str {
+///     "hi"
+///   }
+/// }
+/// // the macro is only used for the types given
+/// impl_Hi!(u16);
+/// fn main() {}
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Hello {
+///   fn hello(&self) -> &'static str;
+/// }
+/// // `for` needs a list of types
+/// #[abstract_impl(for = u8)]
+/// impl Hi for Hello {
+///   fn hello(&self) -> &'static str {
+///     "hi"
+///   }
+/// }
+/// impl_Hi!(u8);
+/// fn main() {}
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Hello {
+///   fn hello(&self) -> &'static str;
+/// }
+/// #[abstract_impl]
+/// impl Greet for Hello {
+///   fn hello(&self) -> &'static str {
+///     "hi"
+///   }
+/// }
+/// // only `for` takes a list
+/// #[abstract_impl(compose = [Greet])]
+/// impl Hi for Hello {}
+/// impl_Hi!(u8);
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Format {
+///   type Error;
+///   fn format(&self) -> Result<String, Self::Error>;
+/// }
+/// trait Report {
+///   type Errors;
+///   fn report(&self) -> Self::Errors;
+/// }
+/// #[abstract_impl]
+/// impl Collect for Report
+/// where
+///   Self: Format + Clone + std::fmt::Debug,
+///   Self: Iterator<Item = u8> + std::ops::Add<Output = u8>,
+///   <Self as Format>::Error: Clone,
+/// {
+///   type Errors = Vec<<Self as Format>::Error>;
+///   fn report(&self) -> Vec<<Self as Format>::Error> {
+///     let first: Option<Self::Item> = self.clone().next();
+///     let sum: Self::Output = self.clone() + self.clone();
+///     let errors = self.format().err().into_iter().chain(first.and(None));
+///     errors.map(|e| Self::Error::clone(&e)).take(sum as usize).collect()
+///   }
+/// }
+/// #[derive(Clone, Debug)]
+/// struct Tens;
+/// impl Format for Tens {
+///   type Error = u8;
+///   fn format(&self) -> Result<String, u8> {
+///     Err(10)
+///   }
+/// }
+/// impl Iterator for Tens {
+///   type Item = u8;
+///   fn next(&mut self) -> Option<u8> {
+///     None
+///   }
+/// }
+/// impl std::ops::Add for Tens {
+///   type Output = u8;
+///   fn add(self, _: Self) -> u8 {
+///     20
+///   }
+/// }
+/// impl_Collect!(Tens);
+/// fn main() {
+///   assert_eq!(vec![10], Tens.report());
+///   let errors: Collect::Errors<Tens> = vec![1];
+///   assert_eq!(vec![1], errors);
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Parse {
+///   type Error;
+///   fn parse(text: &str) -> Result<Self, Self::Error>
+///   where
+///     Self: Sized;
+/// }
+/// trait Named {
+///   fn name() -> &'static str;
+/// }
+/// trait Get {
+///   fn get(text: &str) -> Option<(&'static str, Self)>
+///   where
+///     Self: Sized;
+/// }
+/// // only `Parse` has `Error`, which the compiler finds
+/// #[abstract_impl]
+/// impl ByParse for Get where Self: Parse + Named + Sized {
+///   fn get(text: &str) -> Option<(&'static str, Self)> {
+///     let parsed: Result<Self, Self::Error> = Self::parse(text);
+///     parsed.ok().map(|parsed| (Self::name(), parsed))
+///   }
+/// }
+/// struct Num(u8);
+/// impl Parse for Num {
+///   type Error = std::num::ParseIntError;
+///   fn parse(text: &str) -> Result<Self, Self::Error> {
+///     text.parse().map(Num)
+///   }
+/// }
+/// impl Named for Num {
+///   fn name() -> &'static str {
+///     "num"
+///   }
+/// }
+/// impl_ByParse!(Num);
+/// fn main() {
+///   assert_eq!(Some(("num", 3)), Num::get("3").map(|(name, num)| (name, num.0)));
+///   assert!(Num::get("x").is_none());
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Format {
+///   type Error;
+/// }
+/// trait Parse {
+///   type Error;
+/// }
+/// trait Report {
+///   fn report(&self) -> bool;
+/// }
+/// // `Self::Error` could be of both
+/// #[abstract_impl]
+/// impl Collect for Report where Self: Format + Parse {
+///   fn report(&self) -> bool {
+///     let error: Option<Self::Error> = None;
+///     error.is_some()
+///   }
+/// }
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// #[abstract_impl]
+/// impl Nested for Describe where Self: std::fmt::Debug {
+///   fn describe(&self) -> String {
+///     struct Helper {
+///       text: String,
+///     }
+///     impl Helper {
+///       fn new(text: String) -> Self {
+///         Self { text }
+///       }
+///       fn get(&self) -> &str {
+///         &self.text
+///       }
+///     }
+///     trait Shout {
+///       fn shout(&self) -> String;
+///     }
+///     impl Shout for str {
+///       fn shout(&self) -> String {
+///         self.to_uppercase()
+///       }
+///     }
+///     fn wrap(text: &str) -> String {
+///       format!("[{text}]")
+///     }
+///     let helper = Helper::new(format!("{self:?}"));
+///     wrap(&helper.get().shout())
+///   }
+/// }
+/// #[derive(Debug)]
+/// struct Test;
+/// impl_Nested!(Test);
+/// fn main() {
+///   assert_eq!("[TEST]", Test.describe());
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Absorb: Sized {
+///   fn absorb<T: Into<Self>>(self, t: T) -> Self;
+///   fn same_as<R: PartialEq<Self> + ?Sized>(&self, r: &R) -> bool;
+///   fn sum<'a, I: IntoIterator<Item = &'a Self>>(items: I) -> Option<Self>
+///   where
+///     Self: 'a;
+///   fn find<const N: usize, F: Fn(&Self) -> bool>(items: [Self; N], f: F) -> Option<Self>;
+/// }
+/// #[abstract_impl]
+/// impl ByAdd for Absorb where Self: std::ops::Add<Output = Self> + Copy + PartialEq {
+///   fn absorb<T: Into<Self>>(self, t: T) -> Self {
+///     self + t.into()
+///   }
+///   fn same_as<R: PartialEq<Self> + ?Sized>(&self, r: &R) -> bool {
+///     r == self
+///   }
+///   fn sum<'a, I: IntoIterator<Item = &'a Self>>(items: I) -> Option<Self>
+///   where
+///     Self: 'a,
+///   {
+///     items.into_iter().copied().reduce(|a, b| a + b)
+///   }
+///   fn find<const N: usize, F: Fn(&Self) -> bool>(items: [Self; N], f: F) -> Option<Self> {
+///     items.into_iter().find(|x| f(x))
+///   }
+/// }
+/// impl_ByAdd!(u32);
+/// fn main() {
+///   assert_eq!(3, 1u32.absorb(2u8));
+///   assert!(3u32.same_as(&3u32));
+///   assert_eq!(Some(3), u32::sum(&[1, 2]));
+///   assert_eq!(Some(2), u32::find([1, 2, 3], |x| *x > 1));
+/// }
+/// ```
+/// ```rust
+/// #![deny(warnings)]
+/// use abstract_impl::abstract_impl;
+/// use std::{any::Any, future::Future, pin::Pin, rc::Rc, sync::Arc, task::Poll};
+/// trait Erase {
+///   fn into_any(self: Box<Self>) -> Box<dyn Any>;
+///   fn name(self: Rc<Self>) -> String;
+///   fn count(self: &Arc<Self>) -> usize;
+///   fn reset(&mut self);
+///   fn reset_owned(self) -> Self;
+/// }
+/// #[abstract_impl]
+/// impl ViaDebug for Erase where Self: 'static + std::fmt::Debug + Default {
+///   fn into_any(self: Box<Self>) -> Box<dyn Any> {
+///     self
+///   }
+///   fn name(self: Rc<Self>) -> String {
+///     format!("{self:?}")
+///   }
+///   fn count(self: &Arc<Self>) -> usize {
+///     Arc::strong_count(self)
+///   }
+///   fn reset(&mut self) {
+///     *self = Default::default();
+///   }
+///   fn reset_owned(mut self) -> Self {
+///     self = Default::default();
+///     self
+///   }
+/// }
+/// impl_ViaDebug!(u8);
+/// #[abstract_impl]
+/// impl Ready for Future where Self: Unpin + Copy {
+///   type Output = Self;
+///   fn poll(self: Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> Poll<Self> {
+///     Poll::Ready(*self.get_mut())
+///   }
+/// }
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// struct Now(u8);
+/// impl_Ready!(Now);
+/// fn main() {
+///   assert_eq!(Some(&3), Box::new(3u8).into_any().downcast_ref::<u8>());
+///   assert_eq!("4", Rc::new(4u8).name());
+///   let shared = Arc::new(1u8);
+///   let _other = shared.clone();
+///   assert_eq!(2, shared.count());
+///   let mut value = 5u8;
+///   value.reset();
+///   assert_eq!(0, value);
+///   assert_eq!(0, 5u8.reset_owned());
+///   let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+///   assert_eq!(Poll::Ready(Now(7)), Pin::new(&mut Now(7)).poll(&mut cx));
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait IntoAny {
+///   fn into_any(self: Box<Self>) -> Box<dyn std::any::Any>;
+/// }
+/// // `With` can't take a typed `self`
+/// #[abstract_impl(with)]
+/// impl Boxed for IntoAny where Self: 'static + Sized {
+///   fn into_any(self: Box<Self>) -> Box<dyn std::any::Any> {
+///     self
+///   }
+/// }
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// use std::fmt::Debug;
+/// trait Named {
+///   const PREFIX: &'static str;
+///   type Name;
+///   fn name(&self) -> Self::Name;
+/// }
+/// #[abstract_impl(with)]
+/// impl Prefixed for Named where Self: ?Sized + Debug {
+///   const PREFIX: &'static str = "name";
+///   type Name = String;
+///   fn name(&self) -> String {
+///     format!("{} {self:?}", Self::PREFIX)
+///   }
+/// }
+/// impl_Prefixed!(str);
+/// trait Area {
+///   fn area(&self) -> u32;
+/// }
+/// impl Debug for dyn Area {
+///   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+///     write!(f, "area {}", self.area())
+///   }
+/// }
+/// impl_Prefixed!(dyn Area);
+/// struct Square(u32);
+/// impl Area for Square {
+///   fn area(&self) -> u32 {
+///     self.0 * self.0
+///   }
+/// }
+/// trait Length {
+///   fn length(&self) -> usize;
+/// }
+/// #[abstract_impl(blanket)]
+/// impl ByBytes for Length where Self: ?Sized + AsRef<[u8]> {
+///   fn length(&self) -> usize {
+///     self.as_ref().len()
+///   }
+/// }
+/// trait Duplicate {
+///   fn duplicate(&self) -> Self;
+/// }
+/// #[abstract_impl]
+/// impl ByClone for Duplicate where Self: Clone {
+///   fn duplicate(&self) -> Self {
+///     self.clone()
+///   }
+/// }
+/// impl_ByClone!(u8);
+/// fn main() {
+///   assert_eq!("name \"a\"", "a".name());
+///   let square: &dyn Area = &Square(3);
+///   assert_eq!("name area 9", square.name());
+///   assert_eq!("name [1]", Prefixed::With::from_ref(&[1][..]).name());
+///   assert_eq!(3, "abc".length());
+///   assert_eq!(3, 3u8.duplicate());
+/// }
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Owned {
+///   fn owned(self) -> String;
+/// }
+/// // `self` needs Self to be Sized
+/// #[abstract_impl]
+/// impl ByDisplay for Owned where Self: ?Sized + std::fmt::Display {
+///   fn owned(self) -> String {
+///     self.to_string()
+///   }
+/// }
+/// impl_ByDisplay!(str);
+/// fn main() {}
+/// ```
+/// ```compile_fail
+/// use abstract_impl::abstract_impl;
+/// trait Describe {
+///   fn describe(&self) -> String;
+/// }
+/// // unsized types need `Self: ?Sized`
+/// #[abstract_impl]
+/// impl ByDisplay for Describe where Self: std::fmt::Display {
+///   fn describe(&self) -> String {
+///     self.to_string()
+///   }
+/// }
+/// impl_ByDisplay!(str);
+/// fn main() {}
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Swap {
+///   fn swap_with(&mut self, other: &mut Self);
+/// }
+/// // the body needs Self to be Sized, without it being declared
+/// #[abstract_impl]
+/// impl ByMem for Swap {
+///   fn swap_with(&mut self, other: &mut Self) {
+///     std::mem::swap(self, other)
+///   }
+/// }
+/// impl_ByMem!(u8);
+/// fn main() {
+///   let (mut a, mut b) = (1u8, 2u8);
+///   a.swap_with(&mut b);
+///   assert_eq!((2, 1), (a, b));
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Bytes {
+///   fn bytes(&self) -> impl Iterator<Item = &u8> + '_;
+///   fn counter(&self) -> impl Fn() -> usize;
+///   fn counter_with(&self, extra: impl Fn() -> usize) -> impl Fn() -> usize;
+/// }
+/// // only the closure needs to capture the lifetime of self explicitly
+/// #[abstract_impl]
+/// impl BySlice for Bytes where Self: AsRef<[u8]> {
+///   fn bytes(&self) -> impl Iterator<Item = &u8> + '_ {
+///     self.as_ref().iter()
+///   }
+///   fn counter(&self) -> impl Fn() -> usize {
+///     || self.as_ref().len()
+///   }
+///   fn counter_with(&self, extra: impl Fn() -> usize) -> impl Fn() -> usize {
+///     move || self.as_ref().len() + extra()
+///   }
+/// }
+/// impl_BySlice!(Vec<u8>);
+/// fn main() {
+///   let bytes = vec![1, 2];
+///   assert_eq!(3, bytes.bytes().sum::<u8>());
+///   assert_eq!(2, bytes.counter()());
+///   assert_eq!(3, bytes.counter_with(|| 1)());
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Capacity<T> {
+///   fn capacity(&self) -> usize;
+/// }
+/// #[abstract_impl]
+/// impl<const N: usize, const M: usize> Fixed<T, N, M> for Capacity<T> where T: Default {
+///   fn capacity(&self) -> usize {
+///     N + M
+///   }
+/// }
+/// #[abstract_impl(extends = Fixed<T, { N * 2 }, 0>)]
+/// impl<const N: usize> Doubled<T, N> for Capacity<T> where T: Default {}
+/// mod limits {
+///   pub const CAP: usize = 8;
+///   pub mod inner {
+///     pub const ONE: usize = 1;
+///   }
+/// }
+/// const BASE: usize = 3;
+/// struct A;
+/// struct B;
+/// struct C;
+/// struct D<T>(T);
+/// impl_Fixed!(<u8, { BASE + 1 }, limits::inner::ONE> A, B);
+/// impl_Fixed!(<u8, limits::CAP, limits::CAP> for<T> D<T>);
+/// impl_Doubled!(<u8, { BASE }> C);
+/// fn main() {
+///   assert_eq!(5, A.capacity());
+///   assert_eq!(5, B.capacity());
+///   assert_eq!(16, D(()).capacity());
+///   assert_eq!(6, C.capacity());
+/// }
+/// ```
+/// ```rust
+/// use abstract_impl::abstract_impl;
+/// trait Source<'src> {
+///   fn source(&self) -> &'src str;
+/// }
+/// trait Parse<'src> {
+///   fn first_word(&self) -> &'src str;
+/// }
+/// #[abstract_impl]
+/// impl FirstWord<'src> for Parse<'src> where Self: Source<'src> {
+///   fn first_word(&self) -> &'src str {
+///     self.source().split(' ').next().unwrap_or_default()
+///   }
+/// }
+/// #[abstract_impl]
+/// impl Constant<'static> for Parse<'static> {
+///   fn first_word(&self) -> &'static str {
+///     "constant"
+///   }
+/// }
+/// struct Fixed;
+/// impl Source<'static> for Fixed {
+///   fn source(&self) -> &'static str {
+///     "fixed words"
+///   }
+/// }
+/// struct Other;
+/// impl_FirstWord!(<'static> Fixed);
+/// impl_Constant!(Other);
+/// fn main() {
+///   assert_eq!("fixed", Fixed.first_word());
+///   assert_eq!("constant", Other.first_word());
+/// }
+/// ```
 #[allow(dead_code)]
 struct Tests;
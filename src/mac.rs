@@ -1,143 +1,505 @@
 use std::collections::HashMap;
 
-use proc_macro2::Span;
-use quote::{quote, ToTokens};
+use proc_macro2::{Group, Punct, Spacing, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
 use syn::{
     fold::Fold,
+    parse::{ParseStream, Parser},
+    parse_quote,
     punctuated::Punctuated,
     spanned::Spanned,
-    token::{Brace, Bracket, Comma, Gt, Lt, Not, Paren, PathSep, Pound},
-    AngleBracketedGenericArguments, AttrStyle, Attribute, Block, Expr, ExprConst, ExprPath,
-    FieldPat, FnArg, GenericArgument, GenericParam, Generics, Ident, ImplItem, Item, ItemImpl,
-    ItemMacro, Pat, PatIdent, PatOr, PatParen, PatReference, PatSlice, PatStruct, PatTuple,
-    PatTupleStruct, PatType, Path, PathArguments, PathSegment, Receiver, Stmt, Type, TypeParam,
-    TypePath,
+    token::{Brace, Bracket, Comma, Gt, Lt, Not, Paren, PathSep, Pound, Semi},
+    AngleBracketedGenericArguments, AttrStyle, Attribute, Expr, ExprPath, FieldPat, FnArg,
+    GenericArgument, GenericParam, Generics, Ident, ImplItem, Item, ItemImpl, ItemMacro, Lifetime,
+    Pat, PatIdent, PatOr, PatParen, PatReference, PatSlice, PatStruct, PatTuple, PatTupleStruct,
+    PatType, Path, PathArguments, PathSegment, Receiver, Stmt, Type, TypeParam, TypePath,
+    WherePredicate,
 };
 
-use crate::change_self::ChangeSelfToContext;
+use crate::{
+    change_self::ChangeSelfToContext,
+    dummy::{dummy_item, dummy_predicates, immutable_args},
+    transform::{lifetimes_first, NameImplArgs, Options},
+};
+
+/// The trait implemented by an impl macro
+pub struct MacroTrait {
+    pub path: Path,
+    /// name of the impl macro
+    pub name: Ident,
+    pub hidden: bool,
+    /// items, whose name in the module differs
+    pub renamed: HashMap<Ident, Ident>,
+}
 
 pub fn generate_impl_macro(
     imp: ItemImpl,
     ty: &Ident,
     folder: &mut ChangeSelfToContext,
-    trait_: Path,
+    trait_: MacroTrait,
     generics: Generics,
     ty_generics: Punctuated<GenericArgument, Comma>,
+    options: &Options,
 ) -> Item {
-    let external_types: Box<[_]> = ty_generics
+    let MacroTrait {
+        path: trait_,
+        name,
+        hidden,
+        renamed,
+    } = trait_;
+    let partial = options.partial;
+    // the exported macro calls itself by its path, so that it works without being imported
+    let exported = options.use_macro;
+    let call = macro_path(&Path::from(name.clone()), exported);
+    let lifetimes = impl_lifetimes(&ty_generics);
+    let external_types = external_types(&ty_generics, &folder.const_params);
+    let external_types_use: Box<[_]> = ty_generics.iter().map(use_metavar).collect();
+    // the generics of the impl are replaced by the macro variables directly
+    let new_ty_generics: Box<[_]> = ty_generics
         .iter()
-        .map(|x| {
-            Expr::Verbatim(
-                format!("${}:ty", x.into_token_stream().to_string().to_lowercase())
-                    .parse()
-                    .unwrap(),
-            )
+        .map(|x| match x {
+            GenericArgument::Lifetime(_) => x.clone(),
+            // const args are given as written (syn would add braces to them)
+            _ => GenericArgument::Type(Type::Verbatim(use_metavar(x))),
         })
         .collect();
-    let external_types_use: Box<[_]> = ty_generics
+    let unsafety = imp.unsafety;
+    let items = delegating_items(
+        imp.items.clone(),
+        ty,
+        &renamed,
+        &generics,
+        &ty_generics,
+        &new_ty_generics,
+        folder,
+    );
+    let dummy_items: Box<[_]> = imp
+        .items
         .iter()
-        .map(|x| {
-            Expr::Verbatim(
-                format!("${}", x.into_token_stream().to_string().to_lowercase())
-                    .parse()
-                    .unwrap(),
-            )
-        })
+        .zip(items.iter())
+        .map(|(item, delegating)| dummy_item(item.clone(), delegating, &[]))
         .collect();
-    let new_ty_generics = ty_generics
+    let mut replace_generics = ReplaceGenerics::new(&ty_generics);
+    let items = items
+        .into_vec()
+        .into_iter()
+        .map(|item| replace_generics.fold_impl_item(item))
+        .collect::<Box<_>>();
+    // composed parts give their items by their impl macros
+    let parts: Box<[_]> = options
+        .parts
         .iter()
-        .cloned()
-        .map(|mut x| {
-            if let GenericArgument::Type(Type::Path(p)) = &mut x {
-                p.path.segments[0].ident = Ident::new(
-                    &format!("_impl_{ty}_{}", p.path.segments[0].ident),
-                    p.path.segments[0].ident.span(),
-                )
-            }
-            x
-        })
+        .map(|part| replace_generics.fold_path(part.clone()))
+        .collect();
+    let base = options
+        .base
+        .as_ref()
+        .map(|base| replace_generics.fold_path(base.clone()));
+    let own_dummy_items = dummy_items
+        .into_vec()
+        .into_iter()
+        .map(|item| replace_generics.fold_impl_item(item))
         .collect::<Box<_>>();
-    let items = imp
+    // inherited items are implemented as they are written, so that they use the overriding items
+    let own_source_items = imp
         .items
         .into_iter()
-        .map(|item| match item {
-            ImplItem::Const(c) => {
-                generate_const(c, ty.clone(), generics.clone(), new_ty_generics.clone())
+        .map(|item| replace_generics.fold_impl_item(item))
+        .collect::<Box<_>>();
+    let dummy_items = own_dummy_items
+        .iter()
+        .cloned()
+        .chain(parts.iter().map(|part| call_part(part, "dummy")))
+        .collect::<Box<_>>();
+    // the calls of its own arms and the ones of other macros need the scope
+    let scope = exported || !parts.is_empty() || base.is_some();
+    let own_items = items;
+    let items = own_items
+        .iter()
+        .cloned()
+        .chain(parts.iter().map(|part| call_part(part, "items")))
+        .collect::<Box<_>>();
+    let predicates = generics
+        .where_clause
+        .map(|w| replace_generics.fold_where_clause(w).predicates)
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<Box<_>>();
+    let generics_params = generics.params.clone();
+    let trait_ = replace_generics.fold_path(trait_);
+    // lifetimes have to come before the generics of the caller
+    let (gens_lifetimes, gens): (Vec<_>, Vec<_>) = generics
+        .params
+        .into_iter()
+        .partition(|param| matches!(param, GenericParam::Lifetime(_)));
+    // single items, for wiring an impl from several abstract impls
+    let args_matcher = (!external_types.is_empty()).then_some(quote! {<#(#external_types),*>});
+    let args_use = (!external_types.is_empty()).then_some(quote! {<#(#external_types_use),*>});
+    let own_names: Box<[_]> = own_items.iter().filter_map(item_ident).cloned().collect();
+    let item_arms = own_items
+        .iter()
+        .zip(&own_dummy_items)
+        .zip(&own_source_items)
+        .filter_map(|((item, dummy), source)| {
+            let ident = item_ident(item)?;
+            let item = replace_lifetimes(item.to_token_stream(), &lifetimes);
+            let dummy = replace_lifetimes(dummy.to_token_stream(), &lifetimes);
+            let source = replace_lifetimes(source.to_token_stream(), &lifetimes);
+            // `@select` skips the item, if its name is in the list
+            Some(quote! {
+                (@item #ident #args_matcher) => {
+                    #item
+                };
+                (@dummy #ident #args_matcher) => {
+                    #dummy
+                };
+                (@inherit #ident #args_matcher) => {
+                    #source
+                };
+                (@select $kind:ident #ident [#ident $($rest:tt)*] $($args:tt)*) => {};
+                (@select $kind:ident #ident [$other:tt $($rest:tt)*] $($args:tt)*) => {
+                    #call!(@select $kind #ident [$($rest)*] $($args)*);
+                };
+                (@select $kind:ident #ident [] $($args:tt)*) => {
+                    #call!(@$kind #ident $($args)*);
+                };
+            })
+        });
+    // the own items and the ones of the parts, which aren't skipped
+    let select_own = |kind: TokenStream, skip: TokenStream| {
+        let own = own_names.iter().map(|ident| {
+            quote! {
+                #call!(@select #kind #ident [#skip] #args_use);
             }
-            ImplItem::Fn(f) => {
-                generate_fn(f, ty.clone(), generics.clone(), new_ty_generics.clone())
+        });
+        let parts = parts
+            .iter()
+            .map(|part| call_impl(part, quote! {select #kind [#skip]}, quote! {}));
+        quote! {#(#own)* #(#parts)*}
+    };
+    // the items of the base, which aren't overridden
+    let select_base = |kind: TokenStream, skip: TokenStream| {
+        base.as_ref().map(|base| {
+            call_impl(
+                base,
+                quote! {select #kind [#skip #(#own_names)*]},
+                quote! {},
+            )
+        })
+    };
+    let select_all = {
+        let own = select_own(quote! {$kind}, quote! {$($skip)*});
+        let base = select_base(quote! {$kind}, quote! {$($skip)*});
+        replace_lifetimes(quote! {#own #base}, &lifetimes)
+    };
+    // a derived impl is generated by its base, so that the bounds of both are there
+    // (the items inherited by it are selected as `inherit`)
+    let extend = |kind: TokenStream, select: TokenStream, predicates: &[WherePredicate]| {
+        let extend = match &base {
+            Some(base) => {
+                let own = select_own(select.clone(), quote! {$($skip)*});
+                call_impl(
+                    base,
+                    quote! {
+                        extend #kind $(#[$attr])*
+                        [#(#gens_lifetimes,)* $($generics)* #(#gens,)*]
+                        [#(#predicates,)* $($where)*]
+                        [$($skip)* #(#own_names)*]
+                        [$($items)* #own]
+                    },
+                    quote! {$ty},
+                )
             }
-            ImplItem::Type(t) => generate_type(
-                t,
-                ty.clone(),
-                generics.clone(),
-                new_ty_generics.clone(),
-                folder,
+            None => quote! {
+                $(#[$attr])*
+                #unsafety impl<#(#gens_lifetimes,)* $($generics)* #(#gens),*> #trait_ for $ty
+                where #(#predicates,)* $($where)* {
+                    $($items)*
+                    #call!(@select #select [$($skip)*] #args_use);
+                }
+            },
+        };
+        // other macros call it, so it declares the scope of its own calls
+        let extend = scoped(replace_lifetimes(extend, &lifetimes), scope);
+        quote! {
+            (@extend #kind $(#[$attr:meta])* [$($generics:tt)*] [$($where:tt)*] [$($skip:tt)*] [$($items:tt)*] #args_matcher $ty:ty) => {
+                #extend
+            };
+        }
+    };
+    let extend = [
+        extend(quote! {item}, quote! {inherit}, &predicates),
+        extend(
+            quote! {dummy},
+            quote! {dummy},
+            &dummy_predicates(predicates.to_vec(), &trait_),
+        ),
+    ];
+    let body = |for_generics: TokenStream, for_where: TokenStream| {
+        // partial impls get the remaining items from the caller
+        let caller_items = partial.then_some(quote! {$($($items)*)?});
+        let body = match &base {
+            Some(base) => call_impl(
+                base,
+                quote! {
+                    extend item
+                    [#(#gens_lifetimes,)* #for_generics #(#gens,)*]
+                    [#(#predicates,)* #for_where]
+                    [#(#own_names)*]
+                    [#(#items)* #caller_items]
+                },
+                quote! {$ty},
             ),
-            other => other,
-        })
-        .collect::<Box<_>>();
-    let gens = generics.params;
-    let mut replace_ident = ReplaceIdents(
-        ty_generics
+            None => quote! {
+                #unsafety impl<#(#gens_lifetimes,)* #for_generics #(#gens),*> #trait_ for $ty
+                where #(#predicates,)* #for_where {
+                    #(#items)*
+                    #caller_items
+                }
+            },
+        };
+        replace_lifetimes(body, &lifetimes)
+    };
+    let caller_items = partial.then_some(quote! {$({ $($items:tt)* })?});
+    let caller_items_use = partial.then_some(quote! {$({ $($items)* })?});
+    // the lifetimes of the impl are only declared if the caller doesn't declare generics itself
+    // (and isn't `'static`, so they are filtered first)
+    let plain_body = if lifetimes.is_empty() {
+        body(quote! {}, quote! {})
+    } else {
+        let lifetimes_use = lifetimes
             .iter()
-            .zip(new_ty_generics.iter())
-            .filter_map(|args| match args {
-                (
-                    GenericArgument::Type(Type::Path(old)),
-                    GenericArgument::Type(Type::Path(new)),
-                ) => Some((
-                    old.path.segments[0].ident.to_string(),
-                    new.path.segments[0].ident.to_string(),
-                )),
-                _ => None,
-            })
-            .collect(),
-    );
-    let where_clause = generics
-        .where_clause
-        .map(|w| replace_ident.fold_where_clause(w));
-    let trait_ = replace_ident.fold_path(trait_);
-    Item::Macro(ItemMacro {
-        attrs: vec![Attribute {
+            .map(|l| use_metavar(&GenericArgument::Lifetime(l.clone())));
+        quote! {
+            #call!(@plain [] [#(#lifetimes_use)*] <#(#external_types_use),*> $ty #caller_items_use);
+        }
+    };
+    let plain_arms = (!lifetimes.is_empty()).then(|| {
+        let declared_body = body(quote! {$($declared)*}, quote! {});
+        quote! {
+            (@plain [$($declared:tt)*] ['static $($lifetimes:lifetime)*] $($rest:tt)*) => {
+                #call!(@plain [$($declared)*] [$($lifetimes)*] $($rest)*);
+            };
+            (@plain [$($declared:tt)*] [$lifetime:lifetime $($lifetimes:lifetime)*] $($rest:tt)*) => {
+                #call!(@plain [$($declared)* $lifetime,] [$($lifetimes)*] $($rest)*);
+            };
+            (@plain [$($declared:tt)*] [] <#(#external_types),*> $ty:ty #caller_items) => {
+                #declared_body
+            };
+        }
+    });
+    let for_end = if partial {
+        let for_body = body(quote! {$($for_generics)*,}, quote! {$($for_where)*});
+        // the where clause is read token by token as well, until only the items are left
+        quote! {
+            (@for [#(#external_types),*] [$($for_generics:tt)*] [] > $ty:ty #caller_items) => {
+                #call!(@where [#(#external_types_use),*] [$($for_generics)*] [$ty] [] $({ $($items)* })?);
+            };
+            (@for [$($args:tt)*] [$($for_generics:tt)*] [] > $ty:ty where $($rest:tt)*) => {
+                #call!(@where [$($args)*] [$($for_generics)*] [$ty] [] $($rest)*);
+            };
+            (@where [#(#external_types),*] [$($for_generics:tt)*] [$ty:ty] [$($for_where:tt)*] #caller_items) => {
+                #for_body
+            };
+            (@where [$($args:tt)*] [$($for_generics:tt)*] [$ty:ty] [$($for_where:tt)*] $next:tt $($rest:tt)*) => {
+                #call!(@where [$($args)*] [$($for_generics)*] [$ty] [$($for_where)* $next] $($rest)*);
+            };
+        }
+    } else {
+        let for_body = body(quote! {$($for_generics)*,}, quote! {$($($for_where)*)?});
+        quote! {
+            (@for [#(#external_types),*] [$($for_generics:tt)*] [] > $ty:ty $(where $($for_where:tt)*)?) => {
+                #for_body
+            };
+        }
+    };
+    let inherited = select_base(quote! {item}, quote! {});
+    let all_items = replace_lifetimes(quote! {#(#items)* #inherited}, &lifetimes);
+    let inherited = select_base(quote! {dummy}, quote! {});
+    let dummy_items = replace_lifetimes(quote! {#(#dummy_items)* #inherited}, &lifetimes);
+    // the traits of several are checked by the macro forwarding to their macros
+    let implements = (!hidden).then(|| {
+        implements_arm(
+            std::slice::from_ref(&trait_),
+            &generics_params,
+            &ty_generics,
+            &folder.const_params,
+        )
+    });
+    let tokens = quote! {
+        #implements
+        (@items #args_matcher) => {
+            #all_items
+        };
+        (@dummy #args_matcher) => {
+            #dummy_items
+        };
+        #(#item_arms)*
+        (@select $kind:ident [$($skip:tt)*] #args_matcher) => {
+            #select_all
+        };
+        #(#extend)*
+        #plain_arms
+        #for_end
+        // `for<...>` is read token by token until the closing `>`, keeping track of nested ones
+        (@for [$($args:tt)*] [$($for_generics:tt)*] [<] >> $($rest:tt)*) => {
+            #call!(@for [$($args)*] [$($for_generics)* >] [] > $($rest)*);
+        };
+        (@for [$($args:tt)*] [$($for_generics:tt)*] [< < $($depth:tt)*] >> $($rest:tt)*) => {
+            #call!(@for [$($args)*] [$($for_generics)* >>] [$($depth)*] $($rest)*);
+        };
+        (@for [$($args:tt)*] [$($for_generics:tt)*] [< $($depth:tt)*] > $($rest:tt)*) => {
+            #call!(@for [$($args)*] [$($for_generics)* >] [$($depth)*] $($rest)*);
+        };
+        (@for [$($args:tt)*] [$($for_generics:tt)*] [$($depth:tt)*] < $($rest:tt)*) => {
+            #call!(@for [$($args)*] [$($for_generics)* <] [< $($depth)*] $($rest)*);
+        };
+        (@for [$($args:tt)*] [$($for_generics:tt)*] [$($depth:tt)*] $next:tt $($rest:tt)*) => {
+            #call!(@for [$($args)*] [$($for_generics)* $next] [$($depth)*] $($rest)*);
+        };
+    };
+    // several types are implemented one after another
+    // (the arms called by the user declare the scope of the calls of the macro)
+    let scoped = |tokens: TokenStream| scoped(tokens, scope);
+    let tokens = if external_types.is_empty() {
+        let for_ = scoped(quote! {#call!(@for [] [] [] $($rest)*);});
+        let plain_body = scoped(plain_body);
+        let list = scoped(quote! {
+            #call!($ty #caller_items_use);
+            #call!($($rest)*);
+        });
+        quote! {
+            #tokens
+            (for < $($rest:tt)*) => {
+                #for_
+            };
+            ($ty:ty #caller_items) => {
+                #plain_body
+            };
+            ($ty:ty #caller_items, $($rest:tt)*) => {
+                #list
+            };
+            () => {};
+        }
+    } else {
+        let paths = const_paths(&call, &ty_generics, &folder.const_params, scope);
+        let for_ = scoped(quote! {#call!(@for [#(#external_types_use),*] [] [] $($rest)*);});
+        let plain_body = scoped(plain_body);
+        let generics_list = scoped(quote! {
+            #call!(<#(#external_types_use),*> $ty #caller_items_use);
+            #call!(< $($rest)*);
+        });
+        let list = scoped(quote! {
+            #call!(<#(#external_types_use),*> $ty #caller_items_use);
+            #call!(@then [#(#external_types_use),*] $($rest)*);
+        });
+        // the generics are kept for the following types, until others are given
+        quote! {
+            #tokens
+            #paths
+            (<#(#external_types),*> for < $($rest:tt)*) => {
+                #for_
+            };
+            (<#(#external_types),*> $ty:ty #caller_items) => {
+                #plain_body
+            };
+            (<#(#external_types),*> $ty:ty #caller_items, < $($rest:tt)*) => {
+                #generics_list
+            };
+            (<#(#external_types),*> $ty:ty #caller_items, $($rest:tt)*) => {
+                #list
+            };
+            (@then [$($args:tt)*]) => {};
+            (@then [$($args:tt)*] $($rest:tt)+) => {
+                #call!(<$($args)*> $($rest)*);
+            };
+        }
+    };
+    // without exporting it, the macro is only used for the blanket impl (or the types given with `for`)
+    let mut attrs: Vec<Attribute> = exported
+        .then(|| Attribute {
             pound_token: Pound::default(),
             style: AttrStyle::Outer,
             bracket_token: Bracket::default(),
             meta: syn::Meta::Path(Path::from(Ident::new("macro_export", Span::call_site()))),
-        }],
-        ident: Some(Ident::new(&format!("impl_{}", ty), Span::call_site())),
+        })
+        .into_iter()
+        .collect();
+    if hidden {
+        attrs.push(parse_quote!(#[doc(hidden)]));
+    }
+    Item::Macro(ItemMacro {
+        attrs,
+        ident: Some(name),
         mac: syn::Macro {
             path: Path::from(Ident::new("macro_rules", Span::call_site())),
             bang_token: Not::default(),
             delimiter: syn::MacroDelimiter::Brace(Brace::default()),
-            tokens: if external_types.is_empty() {
-                quote! {
-                    ($t:ty) => {
-                        impl<#gens> #trait_ for $t #where_clause {
-                            #(#items)*
-                        }
-                    };
-                }
-            } else {
-                quote! {
-                    (<#(#external_types),*> $ty:ty) => {
-                        #(#[allow(non_camel_case_types)]type #new_ty_generics = #external_types_use;)*
-                        impl<#gens> #trait_ for $ty #where_clause {
-                            #(#items)*
-                        }
-                    }
-                }
-            },
+            tokens,
         },
         semi_token: None,
     })
 }
 
+/// The items of the impl, using the items of the module
+pub fn delegating_items(
+    items: Vec<ImplItem>,
+    ty: &Ident,
+    renamed: &HashMap<Ident, Ident>,
+    generics: &Generics,
+    ty_generics: &Punctuated<GenericArgument, Comma>,
+    new_ty_generics: &[GenericArgument],
+    folder: &mut ChangeSelfToContext,
+) -> Box<[ImplItem]> {
+    let module_ident = |ident: &Ident| renamed.get(ident).unwrap_or(ident).clone();
+    let new_ty_generics: Box<[_]> = new_ty_generics.into();
+    items
+        .into_iter()
+        .map(|item| match item {
+            ImplItem::Const(c) => {
+                let ident = module_ident(&c.ident);
+                generate_const(
+                    c,
+                    ident,
+                    ty.clone(),
+                    generics.clone(),
+                    new_ty_generics.clone(),
+                )
+            }
+            ImplItem::Fn(f) => {
+                let ident = module_ident(&f.sig.ident);
+                generate_fn(
+                    f,
+                    ident,
+                    ty.clone(),
+                    generics.clone(),
+                    new_ty_generics.clone(),
+                )
+            }
+            ImplItem::Type(t) => {
+                let ident = module_ident(&t.ident);
+                generate_type(
+                    t,
+                    ident,
+                    ty.clone(),
+                    generics.clone(),
+                    ty_generics,
+                    new_ty_generics.clone(),
+                    folder,
+                )
+            }
+            other => other,
+        })
+        .collect()
+}
+
 fn generate_type(
     mut t: syn::ImplItemType,
+    ident: Ident,
     ty: Ident,
     mut generics: Generics,
-    mut ty_generics: Box<[GenericArgument]>,
+    old_ty_generics: &Punctuated<GenericArgument, Comma>,
+    ty_generics: Box<[GenericArgument]>,
     folder: &mut ChangeSelfToContext,
 ) -> ImplItem {
     t.ty = Type::Path(TypePath {
@@ -150,34 +512,45 @@ fn generate_type(
                     arguments: PathArguments::None,
                 },
                 PathSegment {
-                    ident: t.ident.clone(),
+                    ident: ident.clone(),
                     arguments: {
                         let (has_context, retained_generics) =
-                            folder.local_idents.get(&t.ident).unwrap();
+                            folder.local_idents.get(&ident).unwrap();
+                        let retained_generics: Box<[_]> = retained_generics
+                            .iter()
+                            .filter_map(|arg| match arg {
+                                GenericArgument::Type(Type::Path(p)) => p.path.get_ident(),
+                                _ => None,
+                            })
+                            .collect();
                         generics.params = generics
                             .params
                             .into_iter()
                             .filter(|gen| match gen {
                                 GenericParam::Type(TypeParam { ident, .. }) => {
-                                    retained_generics.contains(ident)
+                                    retained_generics.contains(&ident)
                                 }
                                 _ => true,
                             })
                             .collect();
-                        ty_generics = ty_generics
-                            .into_vec()
-                            .into_iter()
-                            .filter(|gen| match gen {
+                        let ty_generics = old_ty_generics
+                            .iter()
+                            .zip(ty_generics)
+                            .filter(|(old, _)| match old {
                                 GenericArgument::Type(Type::Path(p)) => {
-                                    retained_generics.iter().any(|id| {
-                                        p.path.segments[0].ident == format!("_impl_{ty}_{}", id)
-                                    })
+                                    retained_generics.contains(&&p.path.segments[0].ident)
                                 }
                                 _ => true,
                             })
+                            .map(|(_, new)| new)
                             .collect();
-                        let args =
-                            generic_to_arg(t.generics.clone(), *has_context, generics, ty_generics);
+                        let args = generic_to_arg_with_lifetimes(
+                            t.generics.clone(),
+                            *has_context,
+                            generics,
+                            ty_generics,
+                            true,
+                        );
                         if args.is_empty() {
                             PathArguments::None
                         } else {
@@ -198,8 +571,9 @@ fn generate_type(
     ImplItem::Type(t)
 }
 
-fn generate_fn(
+pub fn generate_fn(
     mut f: syn::ImplItemFn,
+    ident: Ident,
     ty: Ident,
     generics: Generics,
     ty_generics: Box<[GenericArgument]>,
@@ -217,43 +591,54 @@ fn generate_fn(
             FnArg::Typed(PatType { pat, .. }) => pat_to_expr(*pat.clone()).remove(0),
         })
         .collect();
-    f.block.stmts = vec![Stmt::Expr(
-        Expr::Call(syn::ExprCall {
+    immutable_args(&mut f.sig);
+    let mut generic_args = generic_to_arg(f.sig.generics.clone(), true, generics, ty_generics);
+    // the named `impl Trait` arguments are inferred
+    let mut impl_args = NameImplArgs::default();
+    for inp in &f.sig.inputs {
+        impl_args.fold_fn_arg(inp.clone());
+    }
+    generic_args.extend(impl_args.params.iter().map(|_| -> GenericArgument {
+        parse_quote! {_}
+    }));
+    let call = Expr::Call(syn::ExprCall {
+        attrs: vec![],
+        func: Box::new(Expr::Path(ExprPath {
             attrs: vec![],
-            func: Box::new(Expr::Path(ExprPath {
-                attrs: vec![],
-                qself: None,
-                path: Path {
-                    leading_colon: None,
-                    segments: [
-                        PathSegment {
-                            ident: ty,
-                            arguments: PathArguments::None,
-                        },
-                        PathSegment {
-                            ident: f.sig.ident.clone(),
-                            arguments: PathArguments::AngleBracketed(
-                                AngleBracketedGenericArguments {
-                                    colon2_token: Some(PathSep::default()),
-                                    lt_token: Lt::default(),
-                                    args: generic_to_arg(
-                                        f.sig.generics.clone(),
-                                        true,
-                                        generics,
-                                        ty_generics,
-                                    ),
-                                    gt_token: Gt::default(),
-                                },
-                            ),
-                        },
-                    ]
-                    .into_iter()
-                    .collect(),
-                },
-            })),
-            paren_token: Paren::default(),
-            args,
-        }),
+            qself: None,
+            path: Path {
+                leading_colon: None,
+                segments: [
+                    PathSegment {
+                        ident: ty,
+                        arguments: PathArguments::None,
+                    },
+                    PathSegment {
+                        ident,
+                        arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments {
+                            colon2_token: Some(PathSep::default()),
+                            lt_token: Lt::default(),
+                            args: generic_args,
+                            gt_token: Gt::default(),
+                        }),
+                    },
+                ]
+                .into_iter()
+                .collect(),
+            },
+        })),
+        paren_token: Paren::default(),
+        args,
+    });
+    let call = match f.sig.unsafety {
+        Some(_) => parse_quote!(unsafe { #call }),
+        None => call,
+    };
+    f.block.stmts = vec![Stmt::Expr(
+        match f.sig.asyncness {
+            Some(_) => parse_quote!(#call.await),
+            None => call,
+        },
         None,
     )];
     ImplItem::Fn(f)
@@ -261,32 +646,15 @@ fn generate_fn(
 
 fn generate_const(
     mut c: syn::ImplItemConst,
+    ident: Ident,
     ty: Ident,
     generics: Generics,
     ty_generics: Box<[GenericArgument]>,
 ) -> ImplItem {
-    c.ty = Type::Path(TypePath {
-        qself: None,
-        path: Path {
-            leading_colon: None,
-            segments: [
-                PathSegment {
-                    ident: ty,
-                    arguments: PathArguments::None,
-                },
-                PathSegment {
-                    ident: c.ident.clone(),
-                    arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments {
-                        colon2_token: None,
-                        lt_token: Lt::default(),
-                        args: generic_to_arg(c.generics.clone(), true, generics, ty_generics),
-                        gt_token: Gt::default(),
-                    }),
-                },
-            ]
-            .into_iter()
-            .collect(),
-        },
+    let args = generic_to_arg(c.generics.clone(), true, generics, ty_generics);
+    // the args may contain macro variables, so this can't be parsed
+    c.expr = Expr::Verbatim(quote! {
+        #ty::#ident::<#args>::VALUE
     });
     ImplItem::Const(c)
 }
@@ -297,56 +665,52 @@ fn generic_to_arg(
     append_generics: Generics,
     ty_generics: Box<[GenericArgument]>,
 ) -> Punctuated<GenericArgument, Comma> {
-    prepend_self
+    generic_to_arg_with_lifetimes(generics, prepend_self, append_generics, ty_generics, false)
+}
+
+/// Lifetimes of functions can't be given explicitly (if late bound), those of types have to be
+fn generic_to_arg_with_lifetimes(
+    generics: Generics,
+    prepend_self: bool,
+    append_generics: Generics,
+    ty_generics: Box<[GenericArgument]>,
+    own_lifetimes: bool,
+) -> Punctuated<GenericArgument, Comma> {
+    let param_to_arg = |param| match param {
+        GenericParam::Lifetime(l) => GenericArgument::Lifetime(l.lifetime),
+        GenericParam::Type(t) => GenericArgument::Type(Type::Path(TypePath {
+            qself: None,
+            path: Path::from(t.ident),
+        })),
+        GenericParam::Const(c) => GenericArgument::Const(Expr::Path(ExprPath {
+            attrs: vec![],
+            qself: None,
+            path: Path::from(c.ident),
+        })),
+    };
+    let mut args: Vec<_> = prepend_self
         .then_some(GenericArgument::Type(Type::Path(TypePath {
             qself: None,
             path: Path::from(Ident::new("Self", Span::call_site())),
         })))
         .into_iter()
-        .chain(ty_generics)
-        .chain(append_generics.params.into_iter().map(|param| match param {
-            GenericParam::Lifetime(l) => GenericArgument::Lifetime(l.lifetime),
-            GenericParam::Type(t) => GenericArgument::Type(Type::Path(TypePath {
-                qself: None,
-                path: Path::from(t.ident),
-            })),
-            GenericParam::Const(c) => GenericArgument::Const(Expr::Path(ExprPath {
-                attrs: vec![],
-                qself: None,
-                path: Path::from(c.ident),
-            })),
-        }))
+        .chain(
+            ty_generics
+                .into_vec()
+                .into_iter()
+                .filter(|arg| own_lifetimes || !matches!(arg, GenericArgument::Lifetime(_))),
+        )
+        .chain(append_generics.params.into_iter().map(param_to_arg))
         .chain(
             generics
                 .params
-                .clone()
                 .into_iter()
-                .filter_map(|param| match param {
-                    GenericParam::Lifetime(_) => None,
-                    GenericParam::Type(t) => Some(GenericArgument::Type(Type::Path(TypePath {
-                        qself: None,
-                        path: Path::from(t.ident),
-                    }))),
-                    GenericParam::Const(c) => {
-                        Some(GenericArgument::Const(Expr::Const(ExprConst {
-                            attrs: vec![],
-                            const_token: c.const_token,
-                            block: Block {
-                                brace_token: Brace::default(),
-                                stmts: vec![Stmt::Expr(
-                                    Expr::Path(ExprPath {
-                                        attrs: vec![],
-                                        qself: None,
-                                        path: Path::from(c.ident),
-                                    }),
-                                    None,
-                                )],
-                            },
-                        })))
-                    }
-                }),
+                .filter(|param| own_lifetimes || !matches!(param, GenericParam::Lifetime(_)))
+                .map(param_to_arg),
         )
-        .collect()
+        .collect();
+    lifetimes_first(&mut args);
+    args.into_iter().collect()
 }
 
 fn pat_to_expr(pat: Pat) -> Vec<Expr> {
@@ -380,13 +744,318 @@ fn pat_to_expr(pat: Pat) -> Vec<Expr> {
     }
 }
 
-struct ReplaceIdents(HashMap<String, String>);
+struct ReplaceGenerics(HashMap<Ident, TokenStream>);
+
+impl ReplaceGenerics {
+    fn new(ty_generics: &Punctuated<GenericArgument, Comma>) -> Self {
+        ReplaceGenerics(
+            ty_generics
+                .iter()
+                .filter_map(|arg| match arg {
+                    GenericArgument::Type(Type::Path(p)) => {
+                        Some((p.path.segments[0].ident.clone(), use_metavar(arg)))
+                    }
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+
+    /// `T` -> `$t`, `T::Assoc` -> `<$t>::Assoc`
+    fn replace(&mut self, path: &Path) -> Option<TokenStream> {
+        let new = self.0.get(&path.segments.first()?.ident)?.clone();
+        if path.segments.len() == 1 && path.segments[0].arguments.is_none() {
+            return Some(new);
+        }
+        let rest = path
+            .segments
+            .iter()
+            .skip(1)
+            .map(|seg| self.fold_path_segment(seg.clone()));
+        Some(quote! {<#new>#(::#rest)*})
+    }
+}
 
-impl syn::fold::Fold for ReplaceIdents {
-    fn fold_ident(&mut self, i: proc_macro2::Ident) -> proc_macro2::Ident {
-        let Some(out) = self.0.get(&i.to_string()) else {
-            return i;
+impl syn::fold::Fold for ReplaceGenerics {
+    fn fold_type(&mut self, i: Type) -> Type {
+        match &i {
+            Type::Path(TypePath { qself: None, path }) => match self.replace(path) {
+                Some(new) => Type::Verbatim(new),
+                None => syn::fold::fold_type(self, i),
+            },
+            _ => syn::fold::fold_type(self, i),
+        }
+    }
+    fn fold_expr(&mut self, i: Expr) -> Expr {
+        match &i {
+            Expr::Path(ExprPath {
+                qself: None, path, ..
+            }) => match self.replace(path) {
+                // `{ N * 2 }` with `{ 3 }` given would be a block followed by a deref
+                Some(new) => Expr::Verbatim(quote! {(#new)}),
+                None => syn::fold::fold_expr(self, i),
+            },
+            _ => syn::fold::fold_expr(self, i),
+        }
+    }
+    fn fold_macro(&mut self, mut i: syn::Macro) -> syn::Macro {
+        // arguments of inherited bodies are replaced, where they parse as expressions (`a, b` or `x; n`)
+        let repeat = |input: ParseStream| {
+            Ok((
+                input.parse::<Expr>()?,
+                input.parse::<Semi>()?,
+                input.parse::<Expr>()?,
+            ))
         };
-        Ident::new(out, i.span())
+        if let Ok(args) = Punctuated::<Expr, Comma>::parse_terminated.parse2(i.tokens.clone()) {
+            let args = args.into_iter().map(|arg| self.fold_expr(arg));
+            i.tokens = quote! {#(#args),*};
+        } else if let Ok((value, semi, len)) = repeat.parse2(i.tokens.clone()) {
+            let (value, len) = (self.fold_expr(value), self.fold_expr(len));
+            i.tokens = quote! {#value #semi #len};
+        }
+        i
+    }
+}
+
+/// The impl macro of an abstract impl and its generic args (`Impl<Args>` -> `impl_Impl`, `<Args>`)
+pub fn impl_macro(mut impl_: Path) -> syn::Result<(Path, Option<TokenStream>)> {
+    let span = impl_.span();
+    let last = impl_
+        .segments
+        .last_mut()
+        .ok_or(syn::Error::new(span, "Expected an impl name"))?;
+    last.ident = format_ident!("impl_{}", last.ident);
+    let args = match std::mem::replace(&mut last.arguments, PathArguments::None) {
+        PathArguments::None => None,
+        PathArguments::AngleBracketed(args) => {
+            let args = args.args;
+            Some(quote!(<#args>))
+        }
+        PathArguments::Parenthesized(p) => {
+            Err(syn::Error::new(p.span(), "Impls are not functions"))?
+        }
+    };
+    Ok((impl_, args))
+}
+
+/// Items of a composed part, given by an arm of its impl macro (`impl_Part!(@items <Args>);`)
+pub fn call_part(part: &Path, arm: &str) -> ImplItem {
+    let arm = Ident::new(arm, Span::call_site());
+    ImplItem::Verbatim(call_impl(part, quote! {#arm}, quote! {}))
+}
+
+/// `impl_Impl!(@arm ... <Args> rest);`
+fn call_impl(impl_: &Path, arm: TokenStream, rest: TokenStream) -> TokenStream {
+    let (mac, args) = impl_macro(impl_.clone()).expect("parts are checked when parsing");
+    // the macros of parts are always exported, to be found by the others
+    let mac = macro_path(&mac, true);
+    quote! {
+        #mac!(@#arm #args #rest);
+    }
+}
+
+/// Exported macros call the ones of their crate by its glob import declared by [`scoped`],
+/// so that they work without being imported
+pub fn macro_path(mac: &Path, exported: bool) -> TokenStream {
+    match mac.get_ident() {
+        Some(ident) if exported => quote! {__impl::#ident},
+        _ => quote! {#mac},
+    }
+}
+
+/// Declares the glob import of the crate of the macros around the output of a macro
+/// (rustc doesn't allow `$crate::impl_Impl!` in the crate defining it)
+pub fn scoped(tokens: TokenStream, scope: bool) -> TokenStream {
+    if !scope {
+        return tokens;
+    }
+    let scope = crate_scope(quote! {$crate});
+    quote! {
+        const _: () = {
+            #scope
+            #tokens
+        };
+    }
+}
+
+/// `mod __impl`, for calling the exported macros of a crate
+pub fn crate_scope(krate: TokenStream) -> TokenStream {
+    quote! {
+        #[allow(unused_imports)]
+        mod __impl {
+            pub(super) use #krate::*;
+        }
+    }
+}
+
+/// The name of an item in an impl
+pub fn item_ident(item: &ImplItem) -> Option<&Ident> {
+    match item {
+        ImplItem::Const(c) => Some(&c.ident),
+        ImplItem::Fn(f) => Some(&f.sig.ident),
+        ImplItem::Type(t) => Some(&t.ident),
+        _ => None,
+    }
+}
+
+/// `@implements [for generics] [for args] [where] [Trait] <Args>` checks, that the traits of the impl are or extend the given one
+///
+/// The generics are those of the type, which the args of the impl may use.
+pub fn implements_arm(
+    traits: &[Path],
+    params: &Punctuated<GenericParam, Comma>,
+    ty_generics: &Punctuated<GenericArgument, Comma>,
+    const_params: &HashMap<Ident, Type>,
+) -> TokenStream {
+    let external_types = external_types(ty_generics, const_params);
+    let args_matcher = (!external_types.is_empty()).then_some(quote! {<#(#external_types),*>});
+    let mut replace_generics = ReplaceGenerics::new(ty_generics);
+    let traits = traits
+        .iter()
+        .map(|trait_| replace_generics.fold_path(trait_.clone()));
+    let impl_lifetimes = impl_lifetimes(ty_generics);
+    // the lifetimes of the impl name are given by the caller
+    let (lifetimes, params): (Vec<_>, Vec<_>) = params
+        .iter()
+        .filter(|param| {
+            !matches!(param, GenericParam::Lifetime(l) if impl_lifetimes.contains(&l.lifetime))
+        })
+        .partition(|param| matches!(param, GenericParam::Lifetime(_)));
+    let check = quote! {
+        #[allow(dead_code)]
+        const _: () = {
+            fn wired<$($generics)* Context: ?Sized + $($trait_)*>() where $($where)* {}
+            fn implements<#(#lifetimes,)* $($generics)* #(#params,)* Context: ?Sized #(+ #traits)*>()
+            where $($where)* {
+                wired::<$($args)* Context>();
+            }
+        };
+    };
+    let check = replace_lifetimes(check, &impl_lifetimes);
+    quote! {
+        (@implements [$($generics:tt)*] [$($args:tt)*] [$($where:tt)*] [$($trait_:tt)*] #args_matcher) => {
+            #check
+        };
+    }
+}
+
+/// The generics of the impl as macro variables (`$t:ty`, `$lt_a:lifetime`, `$n:tt`)
+fn external_types(
+    ty_generics: &Punctuated<GenericArgument, Comma>,
+    const_params: &HashMap<Ident, Type>,
+) -> Box<[Expr]> {
+    ty_generics
+        .iter()
+        .map(|x| {
+            Expr::Verbatim(
+                format!(
+                    "${}:{}",
+                    metavar(x),
+                    match x {
+                        GenericArgument::Lifetime(_) => "lifetime",
+                        // const args are literals, idents or blocks (paths are put in one by `const_paths`)
+                        _ if is_const(x, const_params) => "tt",
+                        _ => "ty",
+                    }
+                )
+                .parse()
+                .unwrap(),
+            )
+        })
+        .collect()
+}
+
+/// Const args given as paths (`limits::CAP`) are put in braces, so that they are a single token
+fn const_paths(
+    call: &TokenStream,
+    ty_generics: &Punctuated<GenericArgument, Comma>,
+    const_params: &HashMap<Ident, Type>,
+    scope: bool,
+) -> TokenStream {
+    let var = |arg: &GenericArgument, pattern: &str| -> TokenStream {
+        pattern.replace('#', &metavar(arg)).parse().unwrap()
+    };
+    // one arm for each const arg, the others may still be paths
+    let arms = ty_generics
+        .iter()
+        .enumerate()
+        .filter(|(_, arg)| is_const(arg, const_params))
+        .map(|(path, _)| {
+            let (matchers, uses): (Vec<_>, Vec<_>) = ty_generics
+                .iter()
+                .enumerate()
+                .map(|(i, arg)| match arg {
+                    GenericArgument::Lifetime(_) => (var(arg, "$#:lifetime"), var(arg, "$#")),
+                    _ if i == path => (
+                        var(arg, "$#:tt $(:: $#_path:tt)+"),
+                        var(arg, "{ $# $(:: $#_path)+ }"),
+                    ),
+                    _ if is_const(arg, const_params) => {
+                        (var(arg, "$($#:tt)::+"), var(arg, "$($#)::+"))
+                    }
+                    _ => (var(arg, "$#:ty"), var(arg, "$#")),
+                })
+                .unzip();
+            let call = scoped(quote! {#call!(<#(#uses),*> $($rest)*);}, scope);
+            quote! {
+                (<#(#matchers),*> $($rest:tt)*) => {
+                    #call
+                };
+            }
+        });
+    quote! {#(#arms)*}
+}
+
+fn is_const(arg: &GenericArgument, const_params: &HashMap<Ident, Type>) -> bool {
+    matches!(arg, GenericArgument::Type(Type::Path(p)) if const_params.contains_key(&p.path.segments[0].ident))
+}
+
+fn impl_lifetimes(ty_generics: &Punctuated<GenericArgument, Comma>) -> Box<[Lifetime]> {
+    ty_generics
+        .iter()
+        .filter_map(|x| match x {
+            GenericArgument::Lifetime(l) => Some(l.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Name of the macro variable for a generic of the impl
+fn metavar(arg: &GenericArgument) -> String {
+    match arg {
+        GenericArgument::Lifetime(l) => format!("lt_{}", l.ident),
+        other => other.to_token_stream().to_string().to_lowercase(),
+    }
+}
+
+fn use_metavar(arg: &GenericArgument) -> TokenStream {
+    format!("${}", metavar(arg)).parse().unwrap()
+}
+
+/// Lifetimes are kept as such in the generated items, so they are replaced by the macro variable afterwards
+fn replace_lifetimes(tokens: TokenStream, lifetimes: &[Lifetime]) -> TokenStream {
+    let mut out = TokenStream::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(p) if p.as_char() == '\'' => match tokens.peek() {
+                Some(TokenTree::Ident(i)) if lifetimes.iter().any(|l| &l.ident == i) => {
+                    out.extend([
+                        TokenTree::Punct(Punct::new('$', Spacing::Alone)),
+                        TokenTree::Ident(Ident::new(&format!("lt_{i}"), i.span())),
+                    ]);
+                    tokens.next();
+                }
+                _ => out.extend([TokenTree::Punct(p)]),
+            },
+            TokenTree::Group(g) => {
+                let mut new = Group::new(g.delimiter(), replace_lifetimes(g.stream(), lifetimes));
+                new.set_span(g.span());
+                out.extend([TokenTree::Group(new)]);
+            }
+            other => out.extend([other]),
+        }
     }
+    out
 }
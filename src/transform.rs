@@ -1,98 +1,305 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use crate::{dummy::generate_dummy_impl, mac::generate_impl_macro};
+use crate::{
+    dummy::generate_dummy_impl,
+    mac::{
+        generate_impl_macro, impl_macro, implements_arm, item_ident, macro_path, scoped, MacroTrait,
+    },
+    with::{generate_with_impl, generate_with_struct},
+};
 
-use super::change_self::ChangeSelfToContext;
-use proc_macro2::Span;
-use quote::quote;
+use super::change_self::{self_traits, take_maybe_sized, ChangeSelfToContext, QualifySelf};
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
 use syn::{
     fold::Fold,
     parse_quote,
     punctuated::Punctuated,
     spanned::Spanned,
     token::{Brace, Bracket, Comma, Mod, Paren, Pound, Pub, Where},
-    AttrStyle, Attribute, Error, GenericArgument, GenericParam, Generics, Ident, ImplItem,
-    ImplItemConst, ImplItemFn, ImplItemType, Item, ItemConst, ItemFn, ItemImpl, ItemMod, ItemType,
-    MetaList, Path, PathArguments, ReturnType, Type, TypeParam, Visibility, WhereClause,
+    AttrStyle, Attribute, ConstParam, Error, FnArg, GenericArgument, GenericParam, Generics, Ident,
+    ImplItem, ImplItemConst, ImplItemFn, ImplItemType, Item, ItemFn, ItemImpl, ItemMod, ItemType,
+    Lifetime, LifetimeParam, MetaList, Pat, PatIdent, Path, PathArguments, Receiver, ReturnType,
+    Signature, TraitItem, Type, TypeParam, TypeParamBound, TypePath, Visibility, WhereClause,
 };
 
-pub fn transform(
-    imp: ItemImpl,
-    use_dummy: bool,
-    use_macro: bool,
-    legacy_order: bool,
-) -> syn::Result<ItemMod> {
-    let copy = imp.clone();
+/// Options given to the attribute
+pub struct Options {
+    pub use_dummy: bool,
+    pub use_macro: bool,
+    pub legacy_order: bool,
+    /// the impl macro takes the missing items
+    pub partial: bool,
+    /// the trait is implemented for every type with the bounds of the impl
+    pub blanket: bool,
+    /// `With<T>` implements the trait for any `T` with the bounds of the impl
+    pub with: bool,
+    /// types the impl macro is called for, where the impl is declared
+    pub targets: Option<TokenStream>,
+    /// abstract impls, whose items are part of this one
+    pub parts: Vec<Path>,
+    /// abstract impl, whose items are used where this one doesn't define them
+    pub base: Option<Path>,
+}
+
+pub fn transform(imp: ItemImpl, options: Options) -> syn::Result<Vec<Item>> {
+    for part in options.parts.iter().chain(&options.base) {
+        impl_macro(part.clone())?;
+    }
+    let mut copy = imp.clone();
     let ItemImpl {
         mut attrs,
-        unsafety,
-        generics,
+        mut generics,
         self_ty,
         items,
         trait_,
         ..
     } = imp;
+    let inherent = trait_.is_none() && !options.legacy_order;
+    let (ty, traits) = match trait_ {
+        Some((_, trait_, _)) if !options.legacy_order => (trait_, trait_paths(*self_ty)?),
+        Some((_, trait_, _)) => (type_path(*self_ty)?, vec![trait_]),
+        // an inherent impl gets a trait of its own, named after it
+        None if inherent => {
+            let ty = type_path(*self_ty)?;
+            let mut extension = ty.clone();
+            let last = &mut extension
+                .segments
+                .last_mut()
+                .expect("paths are never empty")
+                .ident;
+            *last = format_ident!("{last}Ext");
+            (ty, vec![extension])
+        }
+        None => Err(Error::new(copy.span(), "No trait for the impl given"))?,
+    };
+    if traits.len() > 1 && (options.partial || options.base.is_some() || !options.parts.is_empty())
+    {
+        Err(Error::new(
+            ty.span(),
+            "Impls of several traits can't be partial, composed or extended",
+        ))?
+    }
+    if options.blanket && options.partial {
+        Err(Error::new(
+            ty.span(),
+            "Partial impls can't be blanket impls",
+        ))?
+    }
+    if options.with && (options.partial || options.base.is_some() || !options.parts.is_empty()) {
+        Err(Error::new(
+            ty.span(),
+            "`With` can't be generated for partial, composed or extended impls",
+        ))?
+    }
+    if options.with {
+        // `Box<With<T>>` can't be turned into `Box<T>`
+        let typed_receiver = items.iter().find_map(|item| match item {
+            ImplItem::Fn(f) => match f.sig.receiver() {
+                Some(r) if r.colon_token.is_some() => Some(r),
+                _ => None,
+            },
+            _ => None,
+        });
+        if let Some(receiver) = typed_receiver {
+            Err(Error::new(
+                receiver.span(),
+                "`With` can't be generated for methods with a typed `self`",
+            ))?
+        }
+    }
+    // associated items of other traits are qualified, as the free functions couldn't resolve them
+    let mut qualify = QualifySelf {
+        traits: self_traits(&generics),
+        local_idents: items.iter().filter_map(item_ident).cloned().collect(),
+        error: None,
+    };
+    // (a partial or derived impl gets the missing items of its trait)
+    if options.partial || options.base.is_some() {
+        qualify.traits.push(traits[0].clone());
+    }
+    let items: Vec<_> = items
+        .into_iter()
+        .map(|item| qualify.fold_impl_item(item))
+        .collect();
+    generics.where_clause = generics.where_clause.map(|w| qualify.fold_where_clause(w));
+    if let Some(error) = qualify.error {
+        Err(error)?
+    }
+    // Context is Sized, unless the impl declares `Self: ?Sized`
+    let maybe_unsized = take_maybe_sized(&mut generics);
+    copy.items = items.clone();
+    copy.generics.where_clause = generics.where_clause.clone();
+    let groups = assign_items(&traits, items)?;
+    // names used by several traits get the trait as prefix in the module
+    let renamed: Vec<HashMap<_, _>> = traits
+        .iter()
+        .zip(&groups)
+        .map(|(trait_, group)| {
+            group
+                .iter()
+                .filter_map(item_ident)
+                .filter(|ident| {
+                    groups
+                        .iter()
+                        .filter(|other| other.iter().filter_map(item_ident).any(|i| &i == ident))
+                        .count()
+                        > 1
+                })
+                .map(|ident| {
+                    let trait_ = &trait_.segments.last().expect("paths are never empty").ident;
+                    (ident.clone(), format_ident!("{trait_}_{ident}"))
+                })
+                .collect()
+        })
+        .collect();
+    let items: Vec<_> = groups
+        .iter()
+        .zip(&renamed)
+        .flat_map(|(group, renamed)| {
+            group.iter().cloned().map(|mut item| {
+                match &mut item {
+                    ImplItem::Const(ImplItemConst { ident, .. })
+                    | ImplItem::Fn(ImplItemFn {
+                        sig: Signature { ident, .. },
+                        ..
+                    })
+                    | ImplItem::Type(ImplItemType { ident, .. }) => {
+                        if let Some(new) = renamed.get(ident) {
+                            *ident = new.clone();
+                        }
+                    }
+                    _ => {}
+                }
+                item
+            })
+        })
+        .collect();
+    let trait_ = traits[0].clone();
+    (ty.segments.len() == 1)
+        .then_some(())
+        .ok_or(Error::new(ty.span(), "Impl names have to be Idents"))?;
+    let ty_generics = match ty.segments[0].arguments.clone() {
+        PathArguments::None => Punctuated::new(),
+        // `'static` can't be declared, the items use it as it is
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .into_iter()
+            .filter(|arg| !matches!(arg, GenericArgument::Lifetime(l) if l.ident == "static"))
+            .collect(),
+        PathArguments::Parenthesized(p) => Err(Error::new(p.span(), "Impls are not functions"))?,
+    };
+    let ty = ty.segments[0].ident.clone();
+    // generic impls of the trait can only take generics given by it
+    for (used, name) in [(options.blanket, "blanket impls"), (options.with, "`With`")] {
+        for ident in tokens_idents(ty_generics.to_token_stream()) {
+            if used
+                && !traits
+                    .iter()
+                    .any(|trait_| tokens_idents(trait_.to_token_stream()).contains(&ident))
+            {
+                Err(Error::new(
+                    ident.span(),
+                    format!("Generics of {name} have to be used by the trait"),
+                ))?
+            }
+        }
+    }
+    // const params can't be declared in a path, so they are declared on the impl
+    let const_params: HashMap<_, _> = generics
+        .params
+        .iter()
+        .filter_map(|param| {
+            match param {
+            GenericParam::Const(c)
+                if ty_generics.iter().any(|arg| {
+                    matches!(arg, GenericArgument::Type(Type::Path(p)) if p.path.is_ident(&c.ident))
+                }) =>
+            {
+                Some((c.ident.clone(), c.ty.clone()))
+            }
+            _ => None,
+        }
+        })
+        .collect();
+    generics.params = generics
+        .params
+        .into_iter()
+        .filter(
+            |param| !matches!(param, GenericParam::Const(c) if const_params.contains_key(&c.ident)),
+        )
+        .collect();
+    copy.generics.params = generics.params.clone();
+
+    // consts are generated as structs, so all generics have to be given
+    let const_generics: Vec<_> = ty_generics
+        .iter()
+        .filter_map(|arg| match arg {
+            GenericArgument::Type(Type::Path(p)) => Some(p.path.segments[0].ident.clone()),
+            _ => None,
+        })
+        .chain(generics.params.iter().filter_map(|param| match param {
+            GenericParam::Type(t) => Some(t.ident.clone()),
+            GenericParam::Const(c) => Some(c.ident.clone()),
+            GenericParam::Lifetime(_) => None,
+        }))
+        .map(|ident| {
+            GenericArgument::Type(Type::Path(TypePath {
+                qself: None,
+                path: Path::from(ident),
+            }))
+        })
+        .collect();
     let local_idents = items
         .iter()
         .filter_map(|e| match e {
             ImplItem::Type(ty) => Some((ty.ident.clone(), (true, vec![]))),
-            ImplItem::Const(c) => Some((c.ident.clone(), (true, vec![]))),
+            ImplItem::Const(c) => Some((c.ident.clone(), (true, const_generics.clone()))),
             ImplItem::Fn(f) => Some((f.sig.ident.clone(), (true, vec![]))),
             _ => None,
         })
         .collect();
     let mut folder = ChangeSelfToContext {
         local_idents,
+        local_consts: items
+            .iter()
+            .filter_map(|e| match e {
+                ImplItem::Const(c) => Some(c.ident.clone()),
+                _ => None,
+            })
+            .collect(),
+        const_params,
         replaced: false,
         found_idents: std::collections::HashSet::new(),
+        maybe_unsized,
     };
 
-    let trait_ = trait_
-        .ok_or(Error::new(copy.span(), "No trait for the impl given"))?
-        .1;
-    let Type::Path(syn::TypePath {
-        qself: None,
-        path: ty,
-    }) = *self_ty
-    else {
-        Err(Error::new(
-            self_ty.span(),
-            "Impl/Trait name has to be a Path",
-        ))?
-    };
-    let (ty, trait_) = if !legacy_order {
-        (trait_, ty)
-    } else {
-        (ty, trait_)
-    };
-    (ty.segments.len() == 1)
-        .then_some(())
-        .ok_or(Error::new(ty.span(), "Impl names have to be Idents"))?;
-    let ty_generics = match ty.segments[0].arguments.clone() {
-        PathArguments::None => Punctuated::new(),
-        PathArguments::AngleBracketed(args) => args.args,
-        PathArguments::Parenthesized(p) => Err(Error::new(p.span(), "Impls are not functions"))?,
-    };
-    let ty = ty.segments[0].ident.clone();
-
+    // items of a partial (or derived) impl can use the ones given at the impl macro (or inherited)
+    let mut item_generics = generics.clone();
+    if options.partial || options.base.is_some() {
+        item_generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote! {Self: #trait_});
+    }
     let mut processed: Vec<Item> = items
         .into_iter()
         .map(|item| match item {
             ImplItem::Const(c) => Ok(process_const(
                 c,
-                generics.clone(),
+                item_generics.clone(),
                 ty_generics.clone(),
                 &mut folder,
             )?),
             ImplItem::Fn(f) => Ok(process_fn(
                 f,
-                generics.clone(),
+                item_generics.clone(),
                 ty_generics.clone(),
                 &mut folder,
             )?),
             ImplItem::Type(t) => Ok(process_type(
                 t,
-                generics.clone(),
+                item_generics.clone(),
                 ty_generics.clone(),
                 &mut folder,
             )?),
@@ -103,6 +310,11 @@ pub fn transform(
         })
         .collect::<syn::Result<_>>()?;
     processed.push(parse_quote! {use super::*;});
+    // the inherited items can be used from this module as well
+    if let Some(base) = &options.base {
+        let module = base_module(base.clone());
+        processed.push(parse_quote! {pub use #module::*;});
+    }
 
     attrs.push(Attribute {
         pound_token: Pound::default(),
@@ -115,37 +327,187 @@ pub fn transform(
         }),
     });
 
+    // the impls of the traits, each with the items of its trait
+    let copies = traits.iter().zip(groups).map(|(trait_, group)| {
+        let mut copy = copy.clone();
+        copy.items = group;
+        (trait_.clone(), copy)
+    });
     // Dummy Impl (for errors)
     #[cfg(feature = "dummy")]
-    if use_dummy {
-        processed.push(parse_quote! {struct Dummy;});
-        processed.push(generate_dummy_impl(
-            copy.clone(),
-            trait_.clone(),
-            ty_generics.clone(),
-        )?);
+    if options.use_dummy {
+        processed.push(
+            parse_quote! {struct Dummy<Context: ?Sized>(::core::marker::PhantomData<Context>);},
+        );
+        for ((trait_, copy), renamed) in copies.clone().zip(&renamed) {
+            processed.push(generate_dummy_impl(
+                copy,
+                trait_,
+                ty_generics.clone(),
+                &folder.const_params,
+                renamed,
+                &options.parts,
+                options.base.as_ref(),
+            )?);
+        }
+    }
+    // the impl for any value, wrapped in `With`
+    if options.with {
+        processed.extend(generate_with_struct());
+        for ((trait_, copy), renamed) in copies.clone().zip(&renamed) {
+            processed.push(generate_with_impl(
+                copy,
+                &ty,
+                &mut folder,
+                trait_,
+                renamed,
+                generics.clone(),
+                ty_generics.clone(),
+            )?);
+        }
     }
     #[cfg(feature = "macro")]
-    if use_macro {
-        processed.push(generate_impl_macro(
-            copy,
-            &ty,
-            &mut folder,
-            trait_,
-            generics.clone(),
-            ty_generics.clone(),
-        ));
+    if options.use_macro || options.blanket || options.targets.is_some() {
+        let family = traits.len() > 1;
+        let name = format_ident!("impl_{}", ty);
+        let mut names = vec![];
+        for ((trait_, copy), renamed) in copies.zip(renamed) {
+            let last = &trait_.segments.last().expect("paths are never empty").ident;
+            let trait_ = MacroTrait {
+                // every trait of several has its own (hidden) macro
+                name: if family {
+                    format_ident!("{name}_{last}")
+                } else {
+                    name.clone()
+                },
+                hidden: family,
+                path: trait_,
+                renamed,
+            };
+            names.push(trait_.name.clone());
+            processed.push(generate_impl_macro(
+                copy,
+                &ty,
+                &mut folder,
+                trait_,
+                generics.clone(),
+                ty_generics.clone(),
+                &options,
+            ));
+        }
+        if family {
+            let export = options.use_macro.then_some(quote! {#[macro_export]});
+            let implements = implements_arm(
+                &traits,
+                &generics.params,
+                &ty_generics,
+                &folder.const_params,
+            );
+            let names = names
+                .iter()
+                .map(|name| macro_path(&Path::from(name.clone()), options.use_macro));
+            let forward = scoped(quote! {#(#names!($($input)*);)*}, options.use_macro);
+            processed.push(parse_quote! {
+                #export
+                macro_rules! #name {
+                    #implements
+                    ($($input:tt)*) => {
+                        #forward
+                    };
+                }
+            });
+        }
+        if let Some(targets) = &options.targets {
+            processed.push(parse_quote! {
+                #name!(#targets);
+            });
+        }
+        // the blanket impl is generated by the impl macro, with its generics given as they are
+        if options.blanket {
+            let args = (!ty_generics.is_empty()).then_some(quote! {<#ty_generics>});
+            let mut params = impl_params(&ty_generics, &folder.const_params);
+            params.push(context_param(folder.maybe_unsized));
+            lifetimes_first(&mut params);
+            processed.push(parse_quote! {
+                #name!(#args for<#(#params),*> Context);
+            });
+        }
     }
 
-    Ok(ItemMod {
-        attrs,
-        vis: syn::Visibility::Public(Pub::default()),
-        unsafety,
-        mod_token: Mod::default(),
-        ident: ty,
-        content: Some((Brace::default(), processed)),
-        semi: None,
-    })
+    let extension = inherent.then(|| {
+        extension_trait(
+            &traits[0],
+            &ty,
+            impl_params(&ty_generics, &folder.const_params),
+            &copy,
+        )
+    });
+    Ok(extension
+        .into_iter()
+        .chain([Item::Mod(ItemMod {
+            attrs,
+            vis: syn::Visibility::Public(Pub::default()),
+            unsafety: None,
+            mod_token: Mod::default(),
+            ident: ty,
+            content: Some((Brace::default(), processed)),
+            semi: None,
+        })])
+        .collect())
+}
+
+/// The trait of an inherent impl, declaring its items (with their docs)
+fn extension_trait(trait_: &Path, ty: &Ident, params: Vec<GenericParam>, imp: &ItemImpl) -> Item {
+    let ident = &trait_.segments[0].ident;
+    let docs = |attrs: &[Attribute]| -> Vec<Attribute> {
+        attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"))
+            .cloned()
+            .collect()
+    };
+    let items = imp.items.iter().filter_map(|item| -> Option<TraitItem> {
+        match item {
+            ImplItem::Fn(f) => {
+                let docs = docs(&f.attrs);
+                let mut sig = f.sig.clone();
+                // patterns aren't allowed in functions without a body
+                for arg in &mut sig.inputs {
+                    match arg {
+                        FnArg::Receiver(r) if r.reference.is_none() => r.mutability = None,
+                        FnArg::Receiver(_) => {}
+                        FnArg::Typed(arg) => {
+                            *arg.pat = match &*arg.pat {
+                                Pat::Ident(PatIdent { ident, .. }) => parse_quote! {#ident},
+                                _ => parse_quote! {_},
+                            }
+                        }
+                    }
+                }
+                Some(parse_quote! {#(#docs)* #sig;})
+            }
+            ImplItem::Const(c) => {
+                let docs = docs(&c.attrs);
+                let (ident, ty) = (&c.ident, &c.ty);
+                Some(parse_quote! {#(#docs)* const #ident: #ty;})
+            }
+            ImplItem::Type(t) => {
+                let docs = docs(&t.attrs);
+                let (ident, generics) = (&t.ident, &t.generics);
+                let where_clause = &generics.where_clause;
+                Some(parse_quote! {#(#docs)* type #ident #generics #where_clause;})
+            }
+            _ => None,
+        }
+    });
+    let unsafety = imp.unsafety;
+    let doc = format!(" The items of the abstract impl [`{ty}`]");
+    parse_quote! {
+        #[doc = #doc]
+        pub #unsafety trait #ident<#(#params),*> {
+            #(#items)*
+        }
+    }
 }
 
 fn process_type(
@@ -169,7 +531,7 @@ fn process_type(
     folder.replaced = false;
     folder.found_idents = HashSet::new();
     ty = folder.fold_type(ty);
-    generics.where_clause = None;
+    let where_clause = generics.where_clause.take();
     generics = folder.fold_generics(generics);
 
     generics = process_generics(
@@ -179,25 +541,58 @@ fn process_type(
         ty_generics.clone(),
         folder,
     )?;
-    generics.where_clause = None;
+    // keep the own where clause (of GATs), as long as Context is there
+    generics.where_clause = where_clause
+        .map(|mut w| {
+            w.predicates = w
+                .predicates
+                .into_iter()
+                .filter_map(|pred| {
+                    let mut folder = folder.clone();
+                    folder.replaced = false;
+                    let pred = folder.fold_where_predicate(pred);
+                    (!folder.replaced || generics.params.iter().any(is_context)).then_some(pred)
+                })
+                .collect();
+            w
+        })
+        .filter(|w| !w.predicates.is_empty());
     *folder
         .local_idents
         .get_mut(&ident)
         .expect("Not in local_idents! Should be impossible.") = (
         folder.replaced,
-        folder
-            .found_idents
+        // the lifetimes are always declared
+        ty_generics
             .iter()
-            .filter(|id| {
-                append_generics.params.iter().any(|par| match par {
-                    GenericParam::Type(TypeParam { ident, .. }) => ident == *id,
-                    _ => false,
-                }) || ty_generics.iter().any(|arg| match arg {
-                    GenericArgument::Type(Type::Path(p)) => &p.path.segments[0].ident == *id,
-                    _ => false,
-                })
-            })
+            .filter(|arg| matches!(arg, GenericArgument::Lifetime(_)))
             .cloned()
+            .chain(append_generics.params.iter().filter_map(|par| match par {
+                GenericParam::Lifetime(l) => Some(GenericArgument::Lifetime(l.lifetime.clone())),
+                _ => None,
+            }))
+            .chain(
+                folder
+                    .found_idents
+                    .iter()
+                    .filter(|id| {
+                        append_generics.params.iter().any(|par| match par {
+                            GenericParam::Type(TypeParam { ident, .. }) => ident == *id,
+                            _ => false,
+                        }) || ty_generics.iter().any(|arg| match arg {
+                            GenericArgument::Type(Type::Path(p)) => {
+                                &p.path.segments[0].ident == *id
+                            }
+                            _ => false,
+                        })
+                    })
+                    .map(|id| {
+                        GenericArgument::Type(Type::Path(TypePath {
+                            qself: None,
+                            path: Path::from(id.clone()),
+                        }))
+                    }),
+            )
             .collect(),
     );
 
@@ -213,6 +608,145 @@ fn process_type(
     }))
 }
 
+/// All idents in the tokens (also those of lifetimes)
+fn tokens_idents(tokens: proc_macro2::TokenStream) -> Vec<Ident> {
+    tokens
+        .into_iter()
+        .flat_map(|token| match token {
+            proc_macro2::TokenTree::Ident(i) => vec![i],
+            proc_macro2::TokenTree::Group(g) => tokens_idents(g.stream()),
+            _ => vec![],
+        })
+        .collect()
+}
+
+/// `Trait` or several traits at once `(TraitA, TraitB)`
+fn trait_paths(ty: Type) -> syn::Result<Vec<Path>> {
+    match ty {
+        Type::Tuple(t) if !t.elems.is_empty() => t.elems.into_iter().map(type_path).collect(),
+        other => Ok(vec![type_path(other)?]),
+    }
+}
+
+fn type_path(ty: Type) -> syn::Result<Path> {
+    match ty {
+        Type::Path(TypePath { qself: None, path }) => Ok(path),
+        other => Err(Error::new(other.span(), "Impl/Trait name has to be a Path")),
+    }
+}
+
+/// Split the items by trait, given by `#[of(Trait)]` if there are several
+fn assign_items(traits: &[Path], items: Vec<ImplItem>) -> syn::Result<Vec<Vec<ImplItem>>> {
+    let last = |trait_: &Path| {
+        trait_
+            .segments
+            .last()
+            .expect("paths are never empty")
+            .ident
+            .clone()
+    };
+    let mut groups = vec![vec![]; traits.len()];
+    for mut item in items {
+        let attrs = match &mut item {
+            ImplItem::Const(c) => &mut c.attrs,
+            ImplItem::Fn(f) => &mut f.attrs,
+            ImplItem::Type(t) => &mut t.attrs,
+            _ => {
+                groups[0].push(item);
+                continue;
+            }
+        };
+        let of = attrs
+            .iter()
+            .position(|attr| attr.path().is_ident("of"))
+            .map(|pos| attrs.remove(pos).parse_args::<Path>())
+            .transpose()?;
+        let ident = item_ident(&item).expect("only items with names are left");
+        let index = match of {
+            // the trait as written, or the only one with its name
+            Some(of) => {
+                let same_name: Vec<_> = (0..traits.len())
+                    .filter(|&i| last(&traits[i]) == last(&of))
+                    .collect();
+                let written = of.to_token_stream().to_string();
+                let index = traits
+                    .iter()
+                    .position(|trait_| trait_.to_token_stream().to_string() == written);
+                match (index, &*same_name) {
+                    (Some(index), _) | (None, &[index]) => index,
+                    (None, []) => Err(Error::new(of.span(), "Not one of the implemented traits"))?,
+                    (None, _) => Err(Error::new(
+                        of.span(),
+                        "Several of the implemented traits have this name, use its path",
+                    ))?,
+                }
+            }
+            None if traits.len() == 1 => 0,
+            // any of the traits could have the item
+            None => Err(Error::new(
+                ident.span(),
+                format!("The trait of `{ident}` is unclear, use `#[of(Trait)]`"),
+            ))?,
+        };
+        groups[index].push(item);
+    }
+    Ok(groups)
+}
+
+/// The module of the extended abstract impl, seen from inside the generated module
+fn base_module(mut base: Path) -> Path {
+    if let Some(last) = base.segments.last_mut() {
+        last.arguments = PathArguments::None;
+    }
+    let first = &base.segments[0].ident;
+    if base.leading_colon.is_some() || first == "crate" {
+        base
+    } else if first == "self" {
+        base.segments[0].ident = Ident::new("super", first.span());
+        base
+    } else {
+        parse_quote! {super::#base}
+    }
+}
+
+/// The generics of the impl name as params
+pub fn impl_params(
+    ty_generics: &Punctuated<GenericArgument, Comma>,
+    const_params: &HashMap<Ident, Type>,
+) -> Vec<GenericParam> {
+    ty_generics
+        .iter()
+        .map(|arg| match arg {
+            GenericArgument::Lifetime(l) => GenericParam::Lifetime(LifetimeParam::new(l.clone())),
+            other => impl_param(
+                Ident::new(&other.to_token_stream().to_string(), other.span()),
+                const_params,
+            ),
+        })
+        .collect()
+}
+
+/// A generic of the impl name as a param (const if declared as such)
+pub fn impl_param(ident: Ident, const_params: &HashMap<Ident, Type>) -> GenericParam {
+    match const_params.get(&ident) {
+        Some(ty) => parse_quote! {const #ident: #ty},
+        None => GenericParam::Type(TypeParam::from(ident)),
+    }
+}
+
+/// `Context` (`?Sized` if declared by `Self: ?Sized`)
+pub fn context_param(maybe_unsized: bool) -> GenericParam {
+    let mut param = TypeParam::from(Ident::new("Context", Span::mixed_site()));
+    if maybe_unsized {
+        param.bounds.push(parse_quote! {?Sized});
+    }
+    GenericParam::Type(param)
+}
+
+fn is_context(param: &GenericParam) -> bool {
+    matches!(param, GenericParam::Type(TypeParam { ident, .. }) if ident == "Context")
+}
+
 fn process_fn(
     f: ImplItemFn,
     generics: Generics,
@@ -220,12 +754,39 @@ fn process_fn(
     folder: &mut ChangeSelfToContext,
 ) -> syn::Result<Item> {
     let ImplItemFn {
-        attrs,
+        mut attrs,
         mut sig,
         mut block,
         ..
     } = f;
 
+    if sig.unsafety.is_some()
+        && !attrs.iter().any(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(doc),
+                        ..
+                    }),
+                ..
+            }) => path.is_ident("doc") && doc.value().trim() == "# Safety",
+            _ => false,
+        })
+    {
+        attrs.push(parse_quote!(#[doc = " # Safety"]));
+        attrs.push(
+            parse_quote!(#[doc = " The safety contract of the implemented trait method applies."]),
+        );
+    }
+
+    let ref_receiver = matches!(
+        sig.inputs.first(),
+        Some(FnArg::Receiver(Receiver {
+            reference: Some(_),
+            ..
+        }))
+    );
     sig.generics = process_generics(sig.generics, true, generics, ty_generics, folder)?;
     // change Self (to local or Context)
     sig.inputs = sig
@@ -242,6 +803,7 @@ fn process_fn(
         .into_iter()
         .map(|stmt| folder.fold_stmt(stmt))
         .collect();
+    name_lifetimes(&mut sig, ref_receiver);
 
     Ok(Item::Fn(ItemFn {
         attrs,
@@ -251,6 +813,157 @@ fn process_fn(
     }))
 }
 
+/// Free fns don't have the elision rules of methods (`&self` lifetime for the output)
+/// and don't capture lifetimes in `impl Trait` return types (unlike trait methods),
+/// so name all elided lifetimes and capture the ones not mentioned explicitly.
+fn name_lifetimes(sig: &mut Signature, ref_receiver: bool) {
+    let mut namer = NameLifetimes {
+        named: vec![],
+        elided: None,
+    };
+    sig.inputs = sig
+        .inputs
+        .clone()
+        .into_iter()
+        .map(|inp| namer.fold_fn_arg(inp))
+        .collect();
+    if ref_receiver || namer.named.len() == 1 {
+        namer.elided = namer.named.first().cloned();
+    }
+    sig.generics.params = namer
+        .named
+        .iter()
+        .cloned()
+        .map(|lifetime| GenericParam::Lifetime(LifetimeParam::new(lifetime)))
+        .chain(sig.generics.params.clone())
+        .collect();
+    // `use<..>` has to name the types of all arguments
+    let mut args = NameImplArgs::default();
+    sig.inputs = sig
+        .inputs
+        .clone()
+        .into_iter()
+        .map(|inp| args.fold_fn_arg(inp))
+        .collect();
+    sig.generics
+        .params
+        .extend(args.params.into_iter().map(GenericParam::Type));
+    let ReturnType::Type(_, t) = &mut sig.output else {
+        return;
+    };
+    if namer.elided.is_some() {
+        **t = namer.fold_type(*t.clone());
+    }
+    let Type::ImplTrait(t) = &mut **t else {
+        return;
+    };
+    let bounds = t.bounds.to_token_stream();
+    if t.bounds
+        .iter()
+        .any(|bound| matches!(bound, TypeParamBound::PreciseCapture(_)))
+        || sig
+            .generics
+            .lifetimes()
+            .all(|param| mentions_lifetime(bounds.clone(), &param.lifetime))
+    {
+        return;
+    }
+    let (lifetimes, others): (Vec<_>, Vec<_>) = sig
+        .generics
+        .params
+        .iter()
+        .partition(|param| matches!(param, GenericParam::Lifetime(_)));
+    let captured = lifetimes
+        .into_iter()
+        .chain(others)
+        .map(|param| match param {
+            GenericParam::Lifetime(l) => l.lifetime.to_token_stream(),
+            GenericParam::Type(t) => t.ident.to_token_stream(),
+            GenericParam::Const(c) => c.ident.to_token_stream(),
+        });
+    t.bounds.push(parse_quote!(use<#(#captured),*>));
+}
+
+/// Names `impl Trait` arguments (`impl Fn()` -> `_Impl0: Fn()`), given as `_` by the callers
+#[derive(Default)]
+pub struct NameImplArgs {
+    pub params: Vec<TypeParam>,
+}
+impl Fold for NameImplArgs {
+    fn fold_type(&mut self, i: Type) -> Type {
+        match syn::fold::fold_type(self, i) {
+            Type::ImplTrait(t) => {
+                let ident = format_ident!("_Impl{}", self.params.len());
+                self.params.push(TypeParam {
+                    bounds: t.bounds,
+                    ..TypeParam::from(ident.clone())
+                });
+                parse_quote! {#ident}
+            }
+            other => other,
+        }
+    }
+}
+
+/// Names elided lifetimes, a new one each or the one of the output
+struct NameLifetimes {
+    named: Vec<Lifetime>,
+    elided: Option<Lifetime>,
+}
+impl NameLifetimes {
+    fn next(&mut self, span: Span) -> Lifetime {
+        if let Some(lifetime) = &self.elided {
+            return lifetime.clone();
+        }
+        let lifetime = Lifetime::new(&format!("'_impl_{}", self.named.len()), span);
+        self.named.push(lifetime.clone());
+        lifetime
+    }
+}
+impl Fold for NameLifetimes {
+    fn fold_type_reference(&mut self, mut i: syn::TypeReference) -> syn::TypeReference {
+        if i.lifetime.is_none() {
+            i.lifetime = Some(self.next(i.and_token.span()));
+        }
+        i.elem = Box::new(self.fold_type(*i.elem));
+        i
+    }
+    fn fold_lifetime(&mut self, i: Lifetime) -> Lifetime {
+        if i.ident == "_" {
+            self.next(i.span())
+        } else {
+            i
+        }
+    }
+    // elided lifetimes in fn pointers and Fn traits are higher-ranked
+    fn fold_type_bare_fn(&mut self, i: syn::TypeBareFn) -> syn::TypeBareFn {
+        i
+    }
+    fn fold_parenthesized_generic_arguments(
+        &mut self,
+        i: syn::ParenthesizedGenericArguments,
+    ) -> syn::ParenthesizedGenericArguments {
+        i
+    }
+}
+
+/// `impl Trait` captures the lifetimes it mentions (before edition 2024)
+fn mentions_lifetime(tokens: TokenStream, lifetime: &Lifetime) -> bool {
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(p) if p.as_char() == '\'' => {
+                if matches!(tokens.peek(), Some(TokenTree::Ident(i)) if *i == lifetime.ident) {
+                    return true;
+                }
+            }
+            TokenTree::Group(g) if mentions_lifetime(g.stream(), lifetime) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
 fn process_const(
     c: ImplItemConst,
     append_generics: Generics,
@@ -262,32 +975,71 @@ fn process_const(
         const_token,
         ident,
         mut generics,
-        colon_token,
-        ty,
-        eq_token,
+        mut ty,
         mut expr,
-        semi_token,
         ..
     } = c;
 
+    // consts can't be generic, so they are the const of a generic struct instead
     generics = process_generics(generics, true, append_generics, ty_generics, folder)?;
     // change Self (to local or Context)
+    ty = folder.fold_type(ty);
     expr = folder.fold_expr(expr);
 
-    Ok(Item::Const(ItemConst {
-        attrs,
-        vis: Visibility::Public(Pub::default()),
-        const_token,
-        ident,
-        generics,
-        colon_token,
-        ty: Box::new(ty),
-        eq_token,
-        expr: Box::new(expr),
-        semi_token,
+    // the struct only holds the generics, its impl has the bounds
+    let params = generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(l) => GenericParam::Lifetime(LifetimeParam::new(l.lifetime.clone())),
+        GenericParam::Type(t) => {
+            let ident = &t.ident;
+            parse_quote! {#ident: ?Sized}
+        }
+        GenericParam::Const(c) => {
+            let (ident, ty) = (&c.ident, &c.ty);
+            parse_quote! {const #ident: #ty}
+        }
+    });
+    let markers = generics.params.iter().filter_map(|param| match param {
+        GenericParam::Lifetime(l) => {
+            let lifetime = &l.lifetime;
+            Some(quote! {::core::marker::PhantomData<&#lifetime ()>})
+        }
+        GenericParam::Type(t) => {
+            let ident = &t.ident;
+            Some(quote! {::core::marker::PhantomData<#ident>})
+        }
+        GenericParam::Const(_) => None,
+    });
+    let (impl_generics, struct_generics, where_clause) = generics.split_for_impl();
+    Ok(Item::Verbatim(quote! {
+        #(#attrs)*
+        #[allow(non_camel_case_types)]
+        pub struct #ident<#(#params),*>(#(#markers),*);
+        impl #impl_generics #ident #struct_generics #where_clause {
+            pub #const_token VALUE: #ty = #expr;
+        }
     }))
 }
 
+/// Generic params or args, which may be lifetimes
+pub trait MaybeLifetime {
+    fn is_lifetime(&self) -> bool;
+}
+impl MaybeLifetime for GenericParam {
+    fn is_lifetime(&self) -> bool {
+        matches!(self, GenericParam::Lifetime(_))
+    }
+}
+impl MaybeLifetime for GenericArgument {
+    fn is_lifetime(&self) -> bool {
+        matches!(self, GenericArgument::Lifetime(_))
+    }
+}
+
+/// Lifetimes have to come first
+pub fn lifetimes_first(generics: &mut [impl MaybeLifetime]) {
+    generics.sort_by_key(|generic| !generic.is_lifetime());
+}
+
 fn process_generics(
     mut generics: Generics,
     insert_all: bool,
@@ -306,7 +1058,7 @@ fn process_generics(
                         ..
                     }) => {
                         folder.found_idents.contains(&p.path.segments[0].ident)
-                            && p.qself.as_ref().map_or(true, |x| match &*x.ty {
+                            && p.qself.as_ref().is_none_or(|x| match &*x.ty {
                                 Type::Path(p) => {
                                     folder.found_idents.contains(&p.path.segments[0].ident)
                                 }
@@ -329,14 +1081,11 @@ fn process_generics(
         });
     }
     // change Self (to local or Context)
-    generics.params = (insert_all
+    let mut params = (insert_all
         || folder
             .found_idents
             .contains(&Ident::new("Self", Span::mixed_site())))
-    .then_some(syn::GenericParam::Type(TypeParam::from(Ident::new(
-        "Context",
-        Span::mixed_site(),
-    ))))
+    .then(|| context_param(folder.maybe_unsized))
     .into_iter()
     .map(Ok)
     .chain(
@@ -349,35 +1098,45 @@ fn process_generics(
                     colon_token: None,
                     bounds: Punctuated::new(),
                 })),
-                GenericArgument::Type(Type::Path(p)) => Ok(GenericParam::Type(syn::TypeParam {
-                    attrs: vec![],
-                    ident: p.path.segments[0].ident.clone(),
-                    colon_token: None,
-                    bounds: Punctuated::new(),
-                    eq_token: None,
-                    default: None,
-                })),
+                GenericArgument::Type(Type::Path(p)) => Ok(impl_param(
+                    p.path.segments[0].ident.clone(),
+                    &folder.const_params,
+                )),
                 o => Err(Error::new(
                     o.span(),
-                    "Only Type and Lifetime generics are supported on Impl",
+                    "Only Type, Const and Lifetime generics are supported on Impl",
                 )),
             })
             .filter(|param| match param {
-                Ok(GenericParam::Type(TypeParam { ident, .. })) => {
+                Ok(GenericParam::Type(TypeParam { ident, .. }))
+                | Ok(GenericParam::Const(ConstParam { ident, .. })) => {
                     folder.found_idents.contains(ident) || insert_all
                 }
                 _ => true,
             }),
     )
-    .chain(append_generics.params.into_iter().map(Ok))
-    .chain(generics.params.into_iter().map(|param| match param {
-        GenericParam::Type(mut t) => {
-            t.default = t.default.map(|d| folder.clone().fold_type(d));
-            Ok(GenericParam::Type(t))
-        }
-        other => Ok(other),
-    }))
-    .collect::<syn::Result<_>>()?;
+    .chain(
+        append_generics
+            .params
+            .into_iter()
+            .filter(|param| match param {
+                GenericParam::Type(TypeParam { ident, .. }) => {
+                    folder.found_idents.contains(ident) || insert_all
+                }
+                _ => true,
+            })
+            .map(Ok),
+    )
+    // (with their bounds and defaults, which may use Self as well)
+    .chain(
+        generics
+            .params
+            .into_iter()
+            .map(|param| Ok(folder.clone().fold_generic_param(param))),
+    )
+    .collect::<syn::Result<Vec<_>>>()?;
+    lifetimes_first(&mut params);
+    generics.params = params.into_iter().collect();
     generics.where_clause = generics.where_clause.map(|mut w| {
         w.predicates = w
             .predicates
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned, ToTokens};
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    token::{Colon, Comma},
+    DeriveInput, Error, GenericParam, Generics, Ident, Path, Token, Type,
+};
+
+use crate::mac::impl_macro;
+
+/// `unsafe for<T> Type<T>: Trait where T: Bound { item: Impl<Args>, ... }`
+pub struct Wiring {
+    unsafety: Option<Token![unsafe]>,
+    generics: Generics,
+    ty: Type,
+    trait_: Path,
+    items: Punctuated<WiredItem, Comma>,
+}
+
+/// `item: Impl<Args>`
+pub struct WiredItem {
+    ident: Ident,
+    impl_: Path,
+}
+
+impl Parse for Wiring {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let unsafety = input.parse()?;
+        let mut generics = if input.peek(Token![for]) {
+            input.parse::<Token![for]>()?;
+            input.parse()?
+        } else {
+            Generics::default()
+        };
+        let ty = input.parse()?;
+        input.parse::<Colon>()?;
+        let trait_ = input.parse()?;
+        generics.where_clause = input.parse()?;
+        let content;
+        braced!(content in input);
+        Ok(Wiring {
+            unsafety,
+            generics,
+            ty,
+            trait_,
+            items: content.parse_terminated(WiredItem::parse, Comma)?,
+        })
+    }
+}
+
+impl Parse for WiredItem {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.parse()?;
+        input.parse::<Colon>()?;
+        Ok(WiredItem {
+            ident,
+            impl_: input.parse()?,
+        })
+    }
+}
+
+/// Every item is generated by the impl macro of its abstract impl (`impl_Impl!(@item name <Args>)`)
+pub fn generate_wiring(wiring: Wiring) -> syn::Result<TokenStream> {
+    let Wiring {
+        unsafety,
+        generics,
+        ty,
+        trait_,
+        items,
+    } = wiring;
+    let mut wired = HashMap::new();
+    let items = items
+        .into_iter()
+        .map(|WiredItem { ident, impl_ }| {
+            if let Some(other) = wired.insert(ident.clone(), impl_.clone()) {
+                let other = &other.segments.last().expect("paths are never empty").ident;
+                return Err(Error::new(
+                    ident.span(),
+                    format!("`{ident}` is already taken from `{other}`"),
+                ));
+            }
+            let (impl_, args) = impl_macro(impl_)?;
+            Ok(quote! {
+                #impl_!(@item #ident #args);
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #unsafety impl #impl_generics #trait_ for #ty #where_clause {
+            #(#items)*
+        }
+    })
+}
+
+/// The type is implemented by the impl macro of every impl
+pub fn generate_type_wiring(
+    impls: Punctuated<Path, Comma>,
+    item: DeriveInput,
+) -> syn::Result<TokenStream> {
+    let ident = &item.ident;
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let ty = quote! {#ident #ty_generics};
+    let calls = impls
+        .into_iter()
+        .map(|impl_| impl_call(impl_, &item.generics, &ty))
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(quote! {
+        #item
+        #(#calls)*
+    })
+}
+
+/// `for<T> Type<T> where T: Bound { Trait => Impl<Args>, ... } ...`
+pub struct Table(Vec<TypeTable>);
+
+/// `for<T> Type<T> where T: Bound { Trait => Impl<Args>, ... }`
+pub struct TypeTable {
+    generics: Generics,
+    ty: Type,
+    entries: Punctuated<TableEntry, Comma>,
+}
+
+/// `Trait => Impl<Args>`
+pub struct TableEntry {
+    trait_: Path,
+    impl_: Path,
+}
+
+impl Parse for Table {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut tables = vec![];
+        while !input.is_empty() {
+            tables.push(input.parse()?);
+        }
+        Ok(Table(tables))
+    }
+}
+
+impl Parse for TypeTable {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut generics = if input.peek(Token![for]) {
+            input.parse::<Token![for]>()?;
+            input.parse()?
+        } else {
+            Generics::default()
+        };
+        let ty = input.parse()?;
+        generics.where_clause = input.parse()?;
+        let content;
+        braced!(content in input);
+        Ok(TypeTable {
+            generics,
+            ty,
+            entries: content.parse_terminated(TableEntry::parse, Comma)?,
+        })
+    }
+}
+
+impl Parse for TableEntry {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let trait_ = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        Ok(TableEntry {
+            trait_,
+            impl_: input.parse()?,
+        })
+    }
+}
+
+/// Every impl is called once per type, after checking that it implements the traits it is given for
+pub fn generate_table(table: Table) -> syn::Result<TokenStream> {
+    table
+        .0
+        .into_iter()
+        .map(
+            |TypeTable {
+                 generics,
+                 ty,
+                 entries,
+             }| {
+                let mut wired = HashMap::new();
+                let mut impls: Vec<Path> = vec![];
+                let checks = entries
+                    .into_iter()
+                    .map(|TableEntry { trait_, impl_ }| {
+                        let key = trait_.to_token_stream().to_string();
+                        if let Some(other) = wired.insert(key, impl_.clone()) {
+                            let trait_ =
+                                &trait_.segments.last().expect("paths are never empty").ident;
+                            let other =
+                                &other.segments.last().expect("paths are never empty").ident;
+                            return Err(Error::new(
+                                trait_.span(),
+                                format!("`{trait_}` is already wired to `{other}`"),
+                            ));
+                        }
+                        // an impl of several traits is only called once
+                        let same = |other: &Path| {
+                            other.to_token_stream().to_string()
+                                == impl_.to_token_stream().to_string()
+                        };
+                        if !impls.iter().any(same) {
+                            impls.push(impl_.clone());
+                        }
+                        implements_check(trait_, impl_, &generics)
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+                let calls = impls
+                    .into_iter()
+                    .map(|impl_| impl_call(impl_, &generics, &ty))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                Ok(quote! {
+                    #(#checks)*
+                    #(#calls)*
+                })
+            },
+        )
+        .collect()
+}
+
+/// `impl_Impl!(@implements ...)`, failing at the trait if the impl doesn't implement it
+fn implements_check(trait_: Path, impl_: Path, generics: &Generics) -> syn::Result<TokenStream> {
+    let span = trait_.span();
+    let (impl_, args) = impl_macro(impl_)?;
+    let params = generics.params.iter();
+    // lifetimes are inferred
+    let param_args = generics.params.iter().filter_map(|param| match param {
+        GenericParam::Type(t) => Some(&t.ident),
+        GenericParam::Const(c) => Some(&c.ident),
+        GenericParam::Lifetime(_) => None,
+    });
+    let predicates = generics.where_clause.iter().flat_map(|w| &w.predicates);
+    Ok(quote_spanned! {span=>
+        #impl_!(@implements [#(#params,)*] [#(#param_args,)*] [#(#predicates,)*] [#trait_] #args);
+    })
+}
+
+/// `impl_Impl!(<Args> for<T> Type<T> where ...)`
+fn impl_call(impl_: Path, generics: &Generics, ty: &impl ToTokens) -> syn::Result<TokenStream> {
+    let (impl_, args) = impl_macro(impl_)?;
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    Ok(
+        // (a where clause without generics can't be used by the type)
+        if generics.params.is_empty() {
+            quote! {#impl_!(#args #ty);}
+        } else {
+            quote! {#impl_!(#args for #impl_generics #ty #where_clause);}
+        },
+    )
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use proc_macro2::{Group, TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{
+    fold::Fold, parse_quote, punctuated::Punctuated, spanned::Spanned, token::Comma, Error, Expr,
+    ExprPath, FnArg, GenericArgument, Generics, Ident, ImplItem, Item, ItemImpl, Pat, PatIdent,
+    PatType, Path, PathArguments, Receiver, ReturnType, Stmt, Type, TypePath, TypeReference,
+};
+
+use crate::{
+    change_self::{is_self, ChangeSelfToContext},
+    mac::delegating_items,
+    transform::{context_param, impl_params, lifetimes_first},
+};
+
+/// `With(value)` implements the traits of the impl for any value fulfilling its bounds
+pub fn generate_with_struct() -> [Item; 2] {
+    [
+        parse_quote! {
+            /// Implements the trait for any value fulfilling the bounds of the impl (`With(&value)`)
+            #[repr(transparent)]
+            pub struct With<Context: ?Sized>(pub Context);
+        },
+        parse_quote! {
+            impl<Context: ?Sized> With<Context> {
+                /// Use the impl through a reference
+                pub fn from_ref(context: &Context) -> &Self {
+                    // SAFETY: `With` is transparent
+                    unsafe { &*(context as *const Context as *const Self) }
+                }
+                /// Use the impl through a mutable reference
+                pub fn from_mut(context: &mut Context) -> &mut Self {
+                    // SAFETY: `With` is transparent
+                    unsafe { &mut *(context as *mut Context as *mut Self) }
+                }
+            }
+        },
+    ]
+}
+
+/// The trait for `With<Context>`, calling the functions of the module with the wrapped value
+pub fn generate_with_impl(
+    imp: ItemImpl,
+    ty: &Ident,
+    folder: &mut ChangeSelfToContext,
+    trait_: Path,
+    renamed: &HashMap<Ident, Ident>,
+    generics: Generics,
+    ty_generics: Punctuated<GenericArgument, Comma>,
+) -> syn::Result<Item> {
+    let unsafety = imp.unsafety;
+    let new_ty_generics: Box<[_]> = ty_generics.iter().cloned().collect();
+    let items = delegating_items(
+        imp.items,
+        ty,
+        renamed,
+        &generics,
+        &ty_generics,
+        &new_ty_generics,
+        folder,
+    )
+    .into_vec()
+    .into_iter()
+    .map(unwrap_item)
+    .collect::<syn::Result<Vec<_>>>()?;
+    let mut params = impl_params(&ty_generics, &folder.const_params);
+    params.extend(generics.params);
+    params.push(context_param(folder.maybe_unsized));
+    lifetimes_first(&mut params);
+    let predicates = generics
+        .where_clause
+        .into_iter()
+        .flat_map(|w| w.predicates)
+        .map(|pred| replace_self(pred.to_token_stream()));
+    Ok(parse_quote! {
+        #unsafety impl<#(#params),*> #trait_ for With<Context> where #(#predicates,)* {
+            #(#items)*
+        }
+    })
+}
+
+/// The module uses the wrapped value as `Context`
+fn unwrap_item(item: ImplItem) -> syn::Result<ImplItem> {
+    // only `Self` itself can be unwrapped (and wrapped again)
+    let unclear = |ty: &Type| {
+        Error::new(
+            ty.span(),
+            "`With` can't convert this type, only `Self`, `&Self`, `&mut Self` (and returned `Option<Self>` or `Result<Self, _>`)",
+        )
+    };
+    Ok(match item {
+        ImplItem::Fn(mut f) => {
+            let mut unwrap = Unwrap(
+                f.sig
+                    .inputs
+                    .iter()
+                    .map(|arg| match arg {
+                        FnArg::Receiver(Receiver {
+                            colon_token: Some(_),
+                            ty,
+                            ..
+                        }) if !is_self(ty) && !is_self_ref(ty) => Err(unclear(ty)),
+                        FnArg::Receiver(Receiver {
+                            reference,
+                            mutability,
+                            self_token,
+                            ..
+                        }) => {
+                            let self_ = Ident::new("self", self_token.span);
+                            Ok(Some((
+                                self_.clone(),
+                                match (reference, mutability) {
+                                    (None, _) => parse_quote! {#self_.0},
+                                    (Some(_), None) => parse_quote! {&#self_.0},
+                                    (Some(_), Some(_)) => parse_quote! {&mut #self_.0},
+                                },
+                            )))
+                        }
+                        FnArg::Typed(PatType { pat, ty, .. }) => {
+                            let ident = match &**pat {
+                                Pat::Ident(PatIdent { ident, .. }) => ident,
+                                _ if mentions_self(ty) => return Err(unclear(ty)),
+                                _ => return Ok(None),
+                            };
+                            match &**ty {
+                                ty if is_self(ty) => {
+                                    Ok(Some((ident.clone(), parse_quote! {#ident.0})))
+                                }
+                                Type::Reference(TypeReference {
+                                    mutability, elem, ..
+                                }) if is_self(elem) => {
+                                    Ok(Some((ident.clone(), parse_quote! {&#mutability #ident.0})))
+                                }
+                                ty if mentions_self(ty) => Err(unclear(ty)),
+                                _ => Ok(None),
+                            }
+                        }
+                    })
+                    .filter_map(Result::transpose)
+                    .collect::<syn::Result<_>>()?,
+            );
+            f.block = unwrap.fold_block(f.block);
+            // returned values are wrapped again (the block is the call of the module function)
+            let rewrap: Option<fn(Expr) -> Expr> = match &f.sig.output {
+                ReturnType::Type(_, ty) if is_self(ty) => Some(|expr| parse_quote! {With(#expr)}),
+                ReturnType::Type(_, ty) if is_wrapped_self(ty) => {
+                    Some(|expr| parse_quote! {#expr.map(With)})
+                }
+                ReturnType::Type(_, ty) if mentions_self(ty) => return Err(unclear(ty)),
+                _ => None,
+            };
+            if let Some(rewrap) = rewrap {
+                f.block.stmts = f
+                    .block
+                    .stmts
+                    .into_iter()
+                    .map(|stmt| match stmt {
+                        Stmt::Expr(expr, None) => Stmt::Expr(rewrap(expr), None),
+                        other => other,
+                    })
+                    .collect();
+            }
+            ImplItem::Fn(f)
+        }
+        ImplItem::Const(mut c) => {
+            c.expr = Expr::Verbatim(replace_self(c.expr.to_token_stream()));
+            ImplItem::Const(c)
+        }
+        ImplItem::Type(mut t) => {
+            t.ty = Type::Verbatim(replace_self(t.ty.to_token_stream()));
+            ImplItem::Type(t)
+        }
+        other => other,
+    })
+}
+
+fn is_self_ref(ty: &Type) -> bool {
+    matches!(ty, Type::Reference(TypeReference { elem, .. }) if is_self(elem))
+}
+
+/// `Option<Self>` or `Result<Self, E>`
+fn is_wrapped_self(ty: &Type) -> bool {
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return false;
+    };
+    let last = path.segments.last().expect("paths are never empty");
+    let PathArguments::AngleBracketed(args) = &last.arguments else {
+        return false;
+    };
+    let mut args = args.args.iter();
+    (last.ident == "Option" || last.ident == "Result")
+        && matches!(args.next(), Some(GenericArgument::Type(ty)) if is_self(ty))
+        && !args.any(|arg| matches!(arg, GenericArgument::Type(ty) if mentions_self(ty)))
+}
+
+/// `Self` anywhere in the type (but not its associated types, which are the same for `With`)
+fn mentions_self(ty: &Type) -> bool {
+    struct FindSelf(bool);
+    impl Fold for FindSelf {
+        fn fold_type(&mut self, ty: Type) -> Type {
+            match ty {
+                ty if is_self(&ty) => {
+                    self.0 = true;
+                    ty
+                }
+                Type::Path(TypePath { qself: Some(_), .. }) => ty,
+                ty => syn::fold::fold_type(self, ty),
+            }
+        }
+    }
+    let mut find = FindSelf(false);
+    find.fold_type(ty.clone());
+    find.0
+}
+
+/// Arguments of type `Self` are unwrapped, `Self` is the wrapped `Context`
+struct Unwrap(HashMap<Ident, Expr>);
+
+impl Fold for Unwrap {
+    fn fold_expr(&mut self, i: Expr) -> Expr {
+        match &i {
+            Expr::Path(ExprPath {
+                qself: None, path, ..
+            }) => match path.get_ident().and_then(|ident| self.0.get(ident)) {
+                Some(unwrapped) => unwrapped.clone(),
+                None => syn::fold::fold_expr(self, i),
+            },
+            _ => syn::fold::fold_expr(self, i),
+        }
+    }
+    fn fold_ident(&mut self, i: Ident) -> Ident {
+        if i == "Self" {
+            Ident::new("Context", i.span())
+        } else {
+            i
+        }
+    }
+}
+
+fn replace_self(tokens: TokenStream) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(i) if i == "Self" => TokenTree::Ident(Ident::new("Context", i.span())),
+            TokenTree::Group(g) => {
+                let mut new = Group::new(g.delimiter(), replace_self(g.stream()));
+                new.set_span(g.span());
+                TokenTree::Group(new)
+            }
+            other => other,
+        })
+        .collect()
+}